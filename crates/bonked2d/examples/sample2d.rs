@@ -1,7 +1,10 @@
 use bonked2d::{
     make_shared,
     object::{
-        kinematic_body::KinematicBody, static_body::StaticBody, trigger_area::TriggerArea, Object,
+        kinematic_body::KinematicBody,
+        static_body::StaticBody,
+        trigger_area::{Overlap, TriggerArea},
+        Object,
     },
     world::World,
     Mask,
@@ -35,28 +38,33 @@ async fn main() {
         });
 
         macro_rules! draw {
-            ($body:ident, $color:ident) => {
+            ($body:ident, $isometry:expr, $color:ident) => {
                 let body = $body.read();
                 let shape = AShape::new(body.shape());
-                let pos = to_glam(body.isometry().translation.vector);
+                let pos = to_glam($isometry(&*body).translation.vector);
                 shape.draw(pos, $color);
             };
         }
 
+        // run the simulation at a fixed tick rate
+        let alpha = world.step(delta);
+
         for body in world.statics().iter() {
-            draw!(body, BLUE);
+            draw!(body, |b: &StaticBody| *b.isometry(), BLUE);
         }
 
         for body in world.kinematics().iter() {
-            draw!(body, RED);
+            draw!(
+                body,
+                |b: &KinematicBody| b.interpolated_isometry(alpha),
+                RED
+            );
         }
 
         for body in world.triggers().iter() {
-            draw!(body, GREEN);
+            draw!(body, |b: &TriggerArea| *b.isometry(), GREEN);
         }
 
-        world.update(delta);
-
         // quit the example
         if is_quit_requested() {
             break;
@@ -116,7 +124,7 @@ impl<'s> AShape<'s> {
     }
 }
 
-fn build_world() -> World<bool> {
+fn build_world() -> World {
     const EPSILON: Real = 0.0001;
     let mut world = World::with_capacity(EPSILON, 2, 1, 1);
 
@@ -144,14 +152,12 @@ fn build_world() -> World<bool> {
         make_shared(TriggerArea::new(
             shape,
             isometry,
-            false,
+            (),
             Mask::MAX,
-            |trigger: &mut TriggerArea<bool>, _| {
-                let flag = trigger.payload_mut();
-                if !*flag {
-                    *flag = true;
-                    println!("Object entered area");
-                }
+            |_: &mut TriggerArea, _, overlap| match overlap {
+                Overlap::Enter => println!("Object entered area"),
+                Overlap::Exit => println!("Object exited area"),
+                Overlap::Stay => {}
             },
         ))
     });
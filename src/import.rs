@@ -0,0 +1,9 @@
+//! Build the objects of a world from the files of external tools
+
+/// Tiled maps
+#[cfg(feature = "tiled")]
+pub mod tiled;
+
+/// Level meshes
+#[cfg(all(feature = "3d", any(feature = "obj", feature = "gltf")))]
+pub mod mesh;
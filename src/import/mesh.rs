@@ -0,0 +1,216 @@
+//! Import the collision of level meshes saved as Wavefront (.obj) or glTF (.gltf, .glb).
+//! Each mesh becomes a static body, either a triangle mesh or a convex decomposition.
+//!
+//! The names of the meshes carry tags separated by dashes, for instance `rocks-convex-layer2`:
+//! - `trimesh` builds a triangle mesh, hollow but exact,
+//! - `convex` builds a convex decomposition, solid but approximated,
+//! - `nocol` skips the mesh,
+//! - `layerN` puts the body on the layer of bit N, several layer tags can be combined.
+//!
+//! The extras of glTF nodes override the tags, for instance `{ "collider": "convex", "layer": 4 }`.
+
+/// Wavefront meshes
+#[cfg(feature = "obj")]
+mod obj;
+
+/// glTF scenes
+#[cfg(feature = "gltf")]
+mod gltf;
+
+use crate::{
+    make_shared,
+    object::static_body::StaticBody,
+    world::{broadphase::Broadphase, handle::StaticHandle, World},
+    Mask,
+};
+use alloc::{string::String, sync::Arc, vec::Vec};
+use core::fmt;
+use parry::{
+    math::{Isometry, Point, Real},
+    shape::{Shape, SharedShape, TriMesh},
+};
+
+/// Collider key in the extras of a glTF node
+pub const COLLIDER_EXTRA: &str = "collider";
+
+/// Layer key in the extras of a glTF node
+pub const LAYER_EXTRA: &str = "layer";
+
+/// Collision built from a mesh
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MeshCollider {
+    /// Triangle mesh, hollow but exact
+    #[default]
+    TriMesh,
+
+    /// Convex decomposition, solid but approximated
+    Convex,
+
+    /// The mesh has no collision
+    None,
+}
+
+impl MeshCollider {
+    /// Read a collider from its tag
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        match tag.to_ascii_lowercase().as_str() {
+            "trimesh" => Some(Self::TriMesh),
+            "convex" => Some(Self::Convex),
+            "nocol" | "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+}
+
+/// Mesh a static body is created for
+pub struct MeshObject<'a> {
+    /// Name of the node or the object holding the mesh
+    pub name: &'a str,
+
+    /// Collision built from the mesh
+    pub collider: MeshCollider,
+
+    /// Layers of the body
+    pub layer: Mask,
+}
+
+/// Load the level meshes into a world
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MeshLoader {
+    /// Collision built from the meshes without a collider tag
+    collider: MeshCollider,
+}
+
+impl MeshLoader {
+    /// Create a loader building triangle meshes by default
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the collision built from the meshes without a collider tag
+    #[inline]
+    pub fn with_collider(mut self, collider: MeshCollider) -> Self {
+        self.collider = collider;
+        self
+    }
+
+    /// Collision built from the meshes without a collider tag
+    #[inline]
+    pub fn collider(&self) -> MeshCollider {
+        self.collider
+    }
+
+    /// Read the collider and the layers from the tags of a name
+    fn parse_name(&self, name: &str) -> (MeshCollider, Mask) {
+        let mut collider = self.collider;
+        let mut layer: Option<Mask> = None;
+        for tag in name.split('-').skip(1) {
+            if let Some(tagged) = MeshCollider::from_tag(tag) {
+                collider = tagged;
+            } else if let Some(bit) = tag
+                .strip_prefix("layer")
+                .and_then(|bit| bit.parse::<u32>().ok())
+                .filter(|bit| *bit < Mask::BITS)
+            {
+                layer = Some(layer.unwrap_or(0) | 1 << bit);
+            }
+        }
+        (collider, layer.unwrap_or(Mask::MAX))
+    }
+
+    /// Build the collision of a mesh and add it to the world
+    #[allow(clippy::too_many_arguments)]
+    fn add_mesh<T, B, P: Broadphase>(
+        &self,
+        world: &mut World<T, B, P>,
+        object: &MeshObject,
+        isometry: Isometry<Real>,
+        vertices: Vec<Point<Real>>,
+        indices: Vec<[u32; 3]>,
+        payload: &mut impl FnMut(&MeshObject) -> B,
+        handles: &mut Vec<StaticHandle<B>>,
+    ) -> Result<(), MeshError> {
+        if indices.is_empty() {
+            return Ok(());
+        }
+        let count = vertices.len() as u32;
+        if indices.iter().flatten().any(|index| *index >= count) {
+            return Err(MeshError::InvalidMesh(String::from(object.name)));
+        }
+
+        let shape: Arc<dyn Shape> = match object.collider {
+            MeshCollider::None => return Ok(()),
+            MeshCollider::TriMesh => Arc::new(
+                TriMesh::new(vertices, indices)
+                    .map_err(|_| MeshError::InvalidMesh(String::from(object.name)))?,
+            ),
+            MeshCollider::Convex => SharedShape::convex_decomposition(&vertices, &indices).0,
+        };
+
+        let body = StaticBody::new(shape, isometry, payload(object), object.layer);
+        handles.push(world.add_static(make_shared(body)));
+        Ok(())
+    }
+}
+
+/// Error raised while loading meshes
+#[derive(Debug)]
+pub enum MeshError {
+    /// The Wavefront file could not be read
+    #[cfg(feature = "obj")]
+    Obj(tobj::LoadError),
+
+    /// The glTF file could not be read
+    #[cfg(feature = "gltf")]
+    Gltf(::gltf::Error),
+
+    /// The mesh with the given name has indices out of bounds or no valid triangle
+    InvalidMesh(String),
+
+    /// The extra with the given name holds a value which cannot be used
+    #[cfg(feature = "gltf")]
+    InvalidExtra(String),
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "obj")]
+            Self::Obj(error) => write!(f, "invalid Wavefront file: {error}"),
+            #[cfg(feature = "gltf")]
+            Self::Gltf(error) => write!(f, "invalid glTF file: {error}"),
+            Self::InvalidMesh(name) => write!(f, "invalid mesh: {name}"),
+            #[cfg(feature = "gltf")]
+            Self::InvalidExtra(name) => write!(f, "invalid glTF extra: {name}"),
+        }
+    }
+}
+
+impl core::error::Error for MeshError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "obj")]
+            Self::Obj(error) => Some(error),
+            #[cfg(feature = "gltf")]
+            Self::Gltf(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "obj")]
+impl From<tobj::LoadError> for MeshError {
+    #[inline]
+    fn from(error: tobj::LoadError) -> Self {
+        Self::Obj(error)
+    }
+}
+
+#[cfg(feature = "gltf")]
+impl From<::gltf::Error> for MeshError {
+    #[inline]
+    fn from(error: ::gltf::Error) -> Self {
+        Self::Gltf(error)
+    }
+}
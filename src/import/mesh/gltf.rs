@@ -0,0 +1,171 @@
+//! Load the meshes of glTF scenes
+
+use super::{MeshCollider, MeshError, MeshLoader, MeshObject, COLLIDER_EXTRA, LAYER_EXTRA};
+use crate::{
+    world::{broadphase::Broadphase, handle::StaticHandle, World},
+    Mask,
+};
+use ::gltf::{buffer::Data, mesh::Mode, Gltf, Node};
+use alloc::{string::String, vec::Vec};
+use parry::math::{Isometry, Point, Real};
+use parry::na::{Matrix3, Matrix4, Rotation3, Translation3, UnitQuaternion};
+use serde_json::Value;
+use std::path::Path;
+
+impl MeshLoader {
+    /// Load each mesh of the default scene of a glTF file as a static body.
+    /// The bodies take the rigid part of the node transforms,
+    /// the scale of the nodes is applied to the vertices.
+    pub fn load_gltf<T, B, P: Broadphase>(
+        &self,
+        world: &mut World<T, B, P>,
+        path: impl AsRef<Path>,
+        mut payload: impl FnMut(&MeshObject) -> B,
+    ) -> Result<Vec<StaticHandle<B>>, MeshError> {
+        let path = path.as_ref();
+        let Gltf { document, blob } = Gltf::open(path)?;
+        let buffers = ::gltf::import_buffers(&document, path.parent(), blob)?;
+
+        let mut handles = Vec::new();
+        let scene = document
+            .default_scene()
+            .or_else(|| document.scenes().next());
+        if let Some(scene) = scene {
+            for node in scene.nodes() {
+                self.load_node(
+                    world,
+                    &buffers,
+                    &node,
+                    &Matrix4::identity(),
+                    &mut payload,
+                    &mut handles,
+                )?;
+            }
+        }
+        Ok(handles)
+    }
+
+    /// Load the mesh of a node and its children
+    fn load_node<T, B, P: Broadphase>(
+        &self,
+        world: &mut World<T, B, P>,
+        buffers: &[Data],
+        node: &Node,
+        parent: &Matrix4<f32>,
+        payload: &mut impl FnMut(&MeshObject) -> B,
+        handles: &mut Vec<StaticHandle<B>>,
+    ) -> Result<(), MeshError> {
+        let transform = parent * Matrix4::from(node.transform().matrix());
+
+        if let Some(mesh) = node.mesh() {
+            let name = node.name().or(mesh.name()).unwrap_or_default();
+            let (mut collider, mut layer) = self.parse_name(name);
+            for extras in [mesh.extras(), node.extras()].into_iter().flatten() {
+                read_extras(extras.get(), &mut collider, &mut layer)?;
+            }
+            let object = MeshObject {
+                name,
+                collider,
+                layer,
+            };
+
+            // Split the transform between the isometry of the body and the deformation of the vertices.
+            // A mirrored node keeps the reflection in the deformation, which turns the triangles inside out.
+            let (isometry, mirrored) = rigid_part(&transform);
+            let residual = isometry.inverse().to_homogeneous() * transform.cast::<Real>();
+            let winding = |[a, b, c]: [u32; 3]| if mirrored { [a, c, b] } else { [a, b, c] };
+
+            let mut vertices = Vec::new();
+            let mut indices = Vec::new();
+            for primitive in mesh.primitives() {
+                if primitive.mode() != Mode::Triangles {
+                    continue;
+                }
+                let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+                let Some(positions) = reader.read_positions() else {
+                    continue;
+                };
+
+                let offset = vertices.len() as u32;
+                vertices.extend(positions.map(|[x, y, z]| {
+                    let point = Point::new(x, y, z).cast::<Real>();
+                    residual.transform_point(&point)
+                }));
+                let count = vertices.len() as u32 - offset;
+
+                match reader.read_indices() {
+                    Some(read) => {
+                        let read: Vec<u32> = read.into_u32().collect();
+                        if read.iter().any(|index| *index >= count) {
+                            return Err(MeshError::InvalidMesh(String::from(name)));
+                        }
+                        indices.extend(
+                            read.chunks_exact(3)
+                                .map(|i| winding([i[0] + offset, i[1] + offset, i[2] + offset])),
+                        );
+                    }
+                    None => indices.extend((0..count / 3).map(|i| {
+                        winding([offset + 3 * i, offset + 3 * i + 1, offset + 3 * i + 2])
+                    })),
+                }
+            }
+
+            self.add_mesh(
+                world, &object, isometry, vertices, indices, payload, handles,
+            )?;
+        }
+
+        for child in node.children() {
+            self.load_node(world, buffers, &child, &transform, payload, handles)?;
+        }
+        Ok(())
+    }
+}
+
+/// Override the collider and the layer with the extras of a node.
+/// Layers which are negative or exceed the mask size are rejected.
+fn read_extras(raw: &str, collider: &mut MeshCollider, layer: &mut Mask) -> Result<(), MeshError> {
+    let Ok(Value::Object(extras)) = serde_json::from_str::<Value>(raw) else {
+        return Ok(());
+    };
+    if let Some(tagged) = extras
+        .get(COLLIDER_EXTRA)
+        .and_then(Value::as_str)
+        .and_then(MeshCollider::from_tag)
+    {
+        *collider = tagged;
+    }
+    if let Some(value) = extras.get(LAYER_EXTRA) {
+        *layer = value
+            .as_u64()
+            .and_then(|bits| Mask::try_from(bits).ok())
+            .ok_or_else(|| MeshError::InvalidExtra(String::from(LAYER_EXTRA)))?;
+    }
+    Ok(())
+}
+
+/// Extract the translation and the rotation of a transform.
+/// Also tell if the transform is a reflection, which is left out of the rotation.
+fn rigid_part(transform: &Matrix4<f32>) -> (Isometry<Real>, bool) {
+    let transform = transform.cast::<Real>();
+    let translation = Translation3::new(transform[(0, 3)], transform[(1, 3)], transform[(2, 3)]);
+
+    // Remove the scale from the basis before reading the rotation
+    let mut basis: Matrix3<Real> = transform.fixed_view::<3, 3>(0, 0).into_owned();
+    for mut column in basis.column_iter_mut() {
+        let norm = column.norm();
+        if norm > Real::EPSILON {
+            column /= norm;
+        }
+    }
+
+    // Mirror one axis to get a proper rotation
+    let mirrored = basis.determinant() < 0.0;
+    if mirrored {
+        basis.column_mut(0).neg_mut();
+    }
+    let rotation = Rotation3::from_matrix(&basis);
+    let isometry =
+        Isometry::from_parts(translation, UnitQuaternion::from_rotation_matrix(&rotation));
+    (isometry, mirrored)
+}
@@ -0,0 +1,59 @@
+//! Load the objects of Wavefront files
+
+use super::{MeshError, MeshLoader, MeshObject};
+use crate::world::{broadphase::Broadphase, handle::StaticHandle, World};
+use alloc::vec::Vec;
+use parry::math::{Isometry, Point, Real};
+use std::path::Path;
+
+impl MeshLoader {
+    /// Load each object of a Wavefront file as a static body.
+    /// The vertices are already placed in the world, the bodies stay at the origin.
+    pub fn load_obj<T, B, P: Broadphase>(
+        &self,
+        world: &mut World<T, B, P>,
+        path: impl AsRef<Path>,
+        mut payload: impl FnMut(&MeshObject) -> B,
+    ) -> Result<Vec<StaticHandle<B>>, MeshError> {
+        let options = tobj::LoadOptions {
+            triangulate: true,
+            single_index: true,
+            ..Default::default()
+        };
+        let (models, _) = tobj::load_obj(path.as_ref(), &options)?;
+
+        let mut handles = Vec::new();
+        for model in models.iter() {
+            let (collider, layer) = self.parse_name(&model.name);
+            let object = MeshObject {
+                name: &model.name,
+                collider,
+                layer,
+            };
+
+            let vertices = model
+                .mesh
+                .positions
+                .chunks_exact(3)
+                .map(|p| Point::new(p[0], p[1], p[2]).cast::<Real>())
+                .collect();
+            let indices = model
+                .mesh
+                .indices
+                .chunks_exact(3)
+                .map(|i| [i[0], i[1], i[2]])
+                .collect();
+
+            self.add_mesh(
+                world,
+                &object,
+                Isometry::identity(),
+                vertices,
+                indices,
+                &mut payload,
+                &mut handles,
+            )?;
+        }
+        Ok(handles)
+    }
+}
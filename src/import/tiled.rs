@@ -0,0 +1,1364 @@
+//! Import the collision of the maps made with Tiled and saved as JSON (.tmj).
+//! Object layers become static bodies and trigger areas, tile layers become tile maps.
+//! The collision drawn on each tile is matched against the collision types of the tile maps,
+//! the tiles matching none of them become static bodies instead.
+//! Rows of such tiles covered entirely by the same rectangle are merged into a single body.
+//! Tiled measures positions in pixels with the vertical axis pointing down,
+//! they are scaled into world units with the vertical axis pointing up.
+
+use crate::{
+    make_shared,
+    object::{
+        static_body::StaticBody,
+        tile_map::{Corner, Side, Tile, TileId, TileMap},
+        trigger_area::{OnOverlap, TriggerArea},
+    },
+    world::{
+        broadphase::Broadphase,
+        handle::{StaticHandle, TileMapHandle, TriggerHandle},
+        World,
+    },
+    Mask,
+};
+use alloc::{string::String, sync::Arc, vec, vec::Vec};
+use core::fmt;
+use parry::{
+    math::{Isometry, Point, Real, Vector},
+    na::ComplexField,
+    shape::{Ball, Compound, Cuboid, Polyline, Shape, SharedShape, TriMesh},
+    transformation::hertel_mehlhorn,
+};
+use serde::{de::IgnoredAny, Deserialize};
+use serde_json::Value;
+
+/// Integer property defining the layers a static body belongs to
+pub const LAYER_PROPERTY: &str = "layer";
+
+/// Integer property defining the layers a trigger area detects
+pub const MASK_PROPERTY: &str = "mask";
+
+/// Boolean property turning an object into a trigger area
+pub const TRIGGER_PROPERTY: &str = "trigger";
+
+/// Boolean property making a static body only block the bodies coming from above
+pub const ONE_WAY_PROPERTY: &str = "one_way";
+
+/// Flag of the tiles flipped horizontally
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+
+/// Flag of the tiles flipped vertically
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+
+/// Flag of the tiles flipped along their diagonal
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+
+/// Flag of the hexagonal tiles rotated by 120°
+const ROTATED_HEXAGONAL: u32 = 0x1000_0000;
+
+/// Flags stored in the high bits of the tile ids
+const TILE_FLAGS: u32 =
+    FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY | ROTATED_HEXAGONAL;
+
+/// Number of points approximating an ellipse
+const ELLIPSE_POINTS: usize = 16;
+
+/// Distance under which the collision of a tile matches a collision type, relative to the tile size
+const TILE_TOLERANCE: Real = 0.01;
+
+/// Corners of the cells, in the order the slopes are matched
+const CORNERS: [Corner; 4] = [
+    Corner::BottomLeft,
+    Corner::BottomRight,
+    Corner::TopLeft,
+    Corner::TopRight,
+];
+
+/// Sides of the cells, in the order the half blocks are matched
+const SIDES: [Side; 4] = [Side::Bottom, Side::Top, Side::Left, Side::Right];
+
+/// Full turn in radians
+#[allow(clippy::unnecessary_cast)]
+const TAU: Real = core::f32::consts::TAU as Real;
+
+/// Provide the JSON content of an external tileset from its path
+pub type TilesetResolver<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+/// Load the maps made with Tiled into a world
+pub struct TiledLoader<'a> {
+    /// Number of world units per pixel
+    scale: Real,
+
+    /// Provide the JSON content of the external tilesets from their path
+    tilesets: Option<TilesetResolver<'a>>,
+}
+
+impl Default for TiledLoader<'_> {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            tilesets: None,
+        }
+    }
+}
+
+impl<'a> TiledLoader<'a> {
+    /// Create a loader converting the pixels with the given scale
+    #[inline]
+    pub fn new(scale: Real) -> Self {
+        Self {
+            scale,
+            ..Default::default()
+        }
+    }
+
+    /// Resolve the external tilesets (.tsj) with the given function.
+    /// The function receives the path of the tileset as written in the map.
+    #[inline]
+    pub fn with_tilesets(mut self, tilesets: TilesetResolver<'a>) -> Self {
+        self.tilesets = Some(tilesets);
+        self
+    }
+
+    /// Number of world units per pixel
+    #[inline]
+    pub fn scale(&self) -> Real {
+        self.scale
+    }
+
+    /// Parse a map and add its collision to the world.
+    /// Return the handles of the objects added.
+    pub fn load<T, B, P, F>(
+        &self,
+        world: &mut World<T, B, P>,
+        json: &str,
+        factory: &mut F,
+    ) -> Result<TiledLevel<T, B>, TiledError>
+    where
+        P: Broadphase,
+        F: PayloadFactory<T, B>,
+    {
+        let map: RawMap = serde_json::from_str(json)?;
+        if map.orientation != "orthogonal" {
+            return Err(TiledError::Unsupported(
+                "only orthogonal maps are supported",
+            ));
+        }
+        if map.infinite {
+            return Err(TiledError::Unsupported("infinite maps are not supported"));
+        }
+
+        let tilesets = self.resolve(map.tilesets)?;
+        let mut importer = Importer {
+            scale: self.scale,
+            tile_size: Vector::new(map.tilewidth as Real, map.tileheight as Real),
+            tilesets: &tilesets,
+            world,
+            factory,
+            level: TiledLevel {
+                statics: Vec::new(),
+                triggers: Vec::new(),
+                tile_maps: Vec::new(),
+            },
+        };
+        for layer in map.layers.iter() {
+            importer.import_layer(layer, Vector::zeros(), &[])?;
+        }
+        Ok(importer.level)
+    }
+
+    /// Replace the external tilesets by their content, sorted by their first tile id
+    fn resolve(&self, tilesets: Vec<RawTileset>) -> Result<Vec<RawTileset>, TiledError> {
+        let mut resolved = tilesets
+            .into_iter()
+            .map(|tileset| {
+                let Some(source) = tileset.source.as_ref() else {
+                    return Ok(tileset);
+                };
+                let json = self
+                    .tilesets
+                    .and_then(|resolve| resolve(source))
+                    .ok_or_else(|| TiledError::MissingTileset(source.clone()))?;
+                let external: RawTileset = serde_json::from_str(&json)?;
+                Ok(RawTileset {
+                    firstgid: tileset.firstgid,
+                    ..external
+                })
+            })
+            .collect::<Result<Vec<_>, TiledError>>()?;
+        resolved.sort_by_key(|tileset| tileset.firstgid);
+        Ok(resolved)
+    }
+}
+
+/// Create the payloads of the objects imported from a map
+pub trait PayloadFactory<T, B> {
+    /// Payload of a static body
+    fn body(&mut self, object: &TiledObject) -> B;
+
+    /// Payload of a tile map, described by its tile layer.
+    /// Use the payload of a static body by default.
+    #[inline]
+    fn tile_map(&mut self, layer: &TiledObject) -> B {
+        self.body(layer)
+    }
+
+    /// Payload of a trigger area along with its callback
+    fn trigger(&mut self, object: &TiledObject) -> (T, OnOverlap<T, B>);
+}
+
+/// Object or tile of a map an object of the world is created for
+pub struct TiledObject<'a> {
+    /// Identifier of the object or the layer, or id of the tile
+    pub id: u32,
+
+    /// Name of the object or the layer
+    pub name: &'a str,
+
+    /// Class of the object, the tile or the layer
+    pub class: &'a str,
+
+    /// Name of the layer of the object
+    pub layer: &'a str,
+
+    /// Column and row of the tile, if the object comes from a tile layer
+    pub cell: Option<(usize, usize)>,
+
+    /// Properties of the parent layers, followed by the ones of the object
+    pub properties: &'a [Property],
+}
+
+impl TiledObject<'_> {
+    /// Find the value of a property, the object overrides its layers
+    #[inline]
+    pub fn property(&self, name: &str) -> Option<&Value> {
+        self.properties
+            .iter()
+            .rev()
+            .find(|property| property.name == name)
+            .map(|property| &property.value)
+    }
+
+    /// Layers defined by an integer property, all the layers if it is missing.
+    /// Negative values and values exceeding the mask size are rejected.
+    pub fn mask(&self, name: &str) -> Result<Mask, TiledError> {
+        let Some(value) = self.property(name) else {
+            return Ok(Mask::MAX);
+        };
+        value
+            .as_u64()
+            .and_then(|bits| Mask::try_from(bits).ok())
+            .ok_or_else(|| TiledError::InvalidProperty(String::from(name)))
+    }
+
+    /// Check if the object must become a trigger area
+    #[inline]
+    pub fn is_trigger(&self) -> bool {
+        self.property(TRIGGER_PROPERTY)
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+    }
+
+    /// Check if the object must only block the bodies coming from above
+    #[inline]
+    pub fn is_one_way(&self) -> bool {
+        self.property(ONE_WAY_PROPERTY)
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+    }
+}
+
+/// Custom property of a layer, an object or a tile
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Property {
+    /// Name of the property
+    pub name: String,
+
+    /// Type of the property as named by Tiled
+    #[serde(rename = "type", default)]
+    pub kind: String,
+
+    /// Value of the property
+    pub value: Value,
+}
+
+/// Handles of the objects added to the world from a map
+pub struct TiledLevel<T = (), B = ()> {
+    /// Static bodies built from the objects and the tiles
+    pub statics: Vec<StaticHandle<B>>,
+
+    /// Trigger areas built from the objects
+    pub triggers: Vec<TriggerHandle<T, B>>,
+
+    /// Tile maps built from the tile layers
+    pub tile_maps: Vec<TileMapHandle<B>>,
+}
+
+/// Error raised while loading a map
+#[derive(Debug)]
+pub enum TiledError {
+    /// The content is not a valid Tiled map
+    Json(serde_json::Error),
+
+    /// The map relies on a feature the loader does not handle
+    Unsupported(&'static str),
+
+    /// An external tileset could not be found
+    MissingTileset(String),
+
+    /// A property holds a value which cannot be used
+    InvalidProperty(String),
+}
+
+impl fmt::Display for TiledError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(error) => write!(f, "invalid Tiled map: {error}"),
+            Self::Unsupported(feature) => write!(f, "unsupported Tiled map: {feature}"),
+            Self::MissingTileset(source) => write!(f, "missing Tiled tileset: {source}"),
+            Self::InvalidProperty(name) => write!(f, "invalid Tiled property: {name}"),
+        }
+    }
+}
+
+impl core::error::Error for TiledError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Json(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for TiledError {
+    #[inline]
+    fn from(error: serde_json::Error) -> Self {
+        Self::Json(error)
+    }
+}
+
+/// Add the content of a map to a world
+struct Importer<'a, T, B, P: Broadphase, F> {
+    /// Number of world units per pixel
+    scale: Real,
+
+    /// Size of the cells of the map in pixels
+    tile_size: Vector<Real>,
+
+    /// Tilesets sorted by their first tile id
+    tilesets: &'a [RawTileset],
+
+    /// World receiving the objects
+    world: &'a mut World<T, B, P>,
+
+    /// Create the payloads of the objects
+    factory: &'a mut F,
+
+    /// Handles of the objects added so far
+    level: TiledLevel<T, B>,
+}
+
+impl<T, B, P, F> Importer<'_, T, B, P, F>
+where
+    P: Broadphase,
+    F: PayloadFactory<T, B>,
+{
+    /// Import a layer and its children, offset by the given amount of pixels
+    fn import_layer(
+        &mut self,
+        layer: &RawLayer,
+        offset: Vector<Real>,
+        inherited: &[Property],
+    ) -> Result<(), TiledError> {
+        let offset = offset + Vector::new(layer.offsetx, layer.offsety);
+        let properties = inherit(inherited, &layer.properties);
+        match layer.kind.as_str() {
+            "group" => {
+                for child in layer.layers.iter() {
+                    self.import_layer(child, offset, &properties)?;
+                }
+            }
+            "objectgroup" => {
+                for object in layer.objects.iter() {
+                    self.import_object(object, layer, offset, &properties)?;
+                }
+            }
+            "tilelayer" => self.import_tiles(layer, offset, &properties)?,
+            // image layers have no collision
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Turn an object into a static body or a trigger area
+    fn import_object(
+        &mut self,
+        object: &RawObject,
+        layer: &RawLayer,
+        offset: Vector<Real>,
+        inherited: &[Property],
+    ) -> Result<(), TiledError> {
+        let Some((shape, isometry)) = Geometry::from_object(object)
+            .and_then(|geometry| geometry.translated(offset).build(self.scale))
+        else {
+            return Ok(());
+        };
+
+        let properties = inherit(inherited, &object.properties);
+        let info = TiledObject {
+            id: object.id,
+            name: &object.name,
+            class: &object.class,
+            layer: &layer.name,
+            cell: None,
+            properties: &properties,
+        };
+
+        if info.is_trigger() {
+            let (payload, on_overlap) = self.factory.trigger(&info);
+            let area = TriggerArea::new(
+                shape,
+                isometry,
+                payload,
+                info.mask(MASK_PROPERTY)?,
+                on_overlap,
+            );
+            let handle = self.world.add_trigger(make_shared(area));
+            self.level.triggers.push(handle);
+            Ok(())
+        } else {
+            self.add_static(shape, isometry, &info)
+        }
+    }
+
+    /// Turn the tiles of a layer into a tile map.
+    /// The tiles whose collision matches no collision type become static bodies,
+    /// along with the one-way tiles and the ones on other layers than the map.
+    fn import_tiles(
+        &mut self,
+        layer: &RawLayer,
+        offset: Vector<Real>,
+        inherited: &[Property],
+    ) -> Result<(), TiledError> {
+        let tiles = match layer.data.as_ref() {
+            Some(RawData::Array(tiles)) => tiles,
+            Some(RawData::Encoded(_)) => {
+                return Err(TiledError::Unsupported(
+                    "tile layers must be saved with the CSV format",
+                ));
+            }
+            None => return Ok(()),
+        };
+        if layer.width == 0 {
+            return Ok(());
+        }
+
+        let info = TiledObject {
+            id: layer.id,
+            name: &layer.name,
+            class: &layer.class,
+            layer: &layer.name,
+            cell: None,
+            properties: inherited,
+        };
+        let mask = info.mask(LAYER_PROPERTY)?;
+        let square = self.tile_size.x == self.tile_size.y;
+
+        // the palette only holds the collision types used by the layer
+        let mut palette = vec![Tile::Empty];
+        let mut ids: Vec<TileId> = vec![0; tiles.len()];
+
+        let mut run: Option<TileRun> = None;
+        for (index, gid) in tiles.iter().enumerate() {
+            let id = gid & !TILE_FLAGS;
+            let Some((tileset, tile)) = find_tile(self.tilesets, id) else {
+                continue;
+            };
+            let Some(group) = tile.objectgroup.as_ref() else {
+                continue;
+            };
+
+            // large tiles are aligned on the bottom left corner of their cell
+            let cell = (index % layer.width, index / layer.width);
+            let tile_size = Vector::new(tileset.tilewidth as Real, tileset.tileheight as Real);
+            let corner = offset
+                + Vector::new(
+                    cell.0 as Real * self.tile_size.x,
+                    (cell.1 + 1) as Real * self.tile_size.y - tile_size.y,
+                );
+
+            let properties = inherit(inherited, &tile.properties);
+
+            if let [object] = group.objects.as_slice()
+                && tile_size == self.tile_size
+            {
+                let properties = inherit(&properties, &object.properties);
+                let info = TiledObject {
+                    id,
+                    name: &object.name,
+                    class: &tile.class,
+                    layer: &layer.name,
+                    cell: Some(cell),
+                    properties: &properties,
+                };
+
+                // the tiles blocking from every side on the layers of the map go into the map
+                if square
+                    && !info.is_one_way()
+                    && info.mask(LAYER_PROPERTY)? == mask
+                    && let Some(kind) = Geometry::from_object(object)
+                        .and_then(|geometry| geometry.flipped(*gid, tile_size).tile(tile_size.x))
+                {
+                    let position = palette.iter().position(|other| *other == kind);
+                    ids[index] = position.unwrap_or_else(|| {
+                        palette.push(kind);
+                        palette.len() - 1
+                    }) as TileId;
+                    continue;
+                }
+
+                // extend the current row with the tiles filled by a rectangle, whatever their flips
+                if object.fills(tile_size) {
+                    if let Some(run) = run.as_mut()
+                        && run.extends(cell, &tile.class, object, &properties)
+                    {
+                        run.length += 1;
+                        continue;
+                    }
+                    if let Some(run) = run.take() {
+                        self.add_run(run, layer, offset)?;
+                    }
+                    run = Some(TileRun {
+                        id,
+                        class: &tile.class,
+                        object,
+                        cell,
+                        length: 1,
+                        properties,
+                    });
+                    continue;
+                }
+            }
+
+            let mut built = Vec::new();
+            for object in group.objects.iter() {
+                if let Some(collider) = Geometry::from_object(object).and_then(|geometry| {
+                    geometry
+                        .flipped(*gid, tile_size)
+                        .translated(corner)
+                        .build(self.scale)
+                }) {
+                    built.push((collider, inherit(&properties, &object.properties), object));
+                }
+            }
+
+            for ((shape, isometry), properties, object) in built {
+                let info = TiledObject {
+                    id,
+                    name: &object.name,
+                    class: &tile.class,
+                    layer: &layer.name,
+                    cell: Some(cell),
+                    properties: &properties,
+                };
+                self.add_static(shape, isometry, &info)?;
+            }
+        }
+        if let Some(run) = run {
+            self.add_run(run, layer, offset)?;
+        }
+        if palette.len() == 1 {
+            return Ok(());
+        }
+
+        // the rows of the map start from the bottom, the ones of the layer from the top
+        let height = tiles.len().div_ceil(layer.width);
+        let origin = offset + Vector::new(0.0, height as Real * self.tile_size.y);
+        let payload = self.factory.tile_map(&info);
+        let mut map = TileMap::new(
+            Vector::new(origin.x, -origin.y) * self.scale,
+            self.tile_size.x * self.scale,
+            layer.width,
+            height,
+            palette,
+            payload,
+            mask,
+        );
+        for (index, id) in ids.into_iter().enumerate() {
+            if id != 0 {
+                let cell = (index % layer.width, height - 1 - index / layer.width);
+                map.set_tile_id(cell, id);
+            }
+        }
+        let handle = self.world.add_tile_map(make_shared(map));
+        self.level.tile_maps.push(handle);
+        Ok(())
+    }
+
+    /// Add a single static body covering a row of tiles
+    fn add_run(
+        &mut self,
+        run: TileRun,
+        layer: &RawLayer,
+        offset: Vector<Real>,
+    ) -> Result<(), TiledError> {
+        let half_extents =
+            Vector::new(run.length as Real * self.tile_size.x, self.tile_size.y) * 0.5;
+        let corner = offset
+            + Vector::new(
+                run.cell.0 as Real * self.tile_size.x,
+                run.cell.1 as Real * self.tile_size.y,
+            );
+        let geometry = Geometry::Cuboid {
+            center: Point::from(corner + half_extents),
+            half_extents,
+            angle: 0.0,
+        };
+        let Some((shape, isometry)) = geometry.build(self.scale) else {
+            return Ok(());
+        };
+
+        let info = TiledObject {
+            id: run.id,
+            name: &run.object.name,
+            class: run.class,
+            layer: &layer.name,
+            cell: Some(run.cell),
+            properties: &run.properties,
+        };
+        self.add_static(shape, isometry, &info)
+    }
+
+    /// Add a static body to the world
+    fn add_static(
+        &mut self,
+        shape: Arc<dyn Shape>,
+        isometry: Isometry<Real>,
+        info: &TiledObject,
+    ) -> Result<(), TiledError> {
+        let layer = info.mask(LAYER_PROPERTY)?;
+        let payload = self.factory.body(info);
+        let mut body = StaticBody::new(shape, isometry, payload, layer);
+        if info.is_one_way() {
+            body.set_one_way(Some(Vector::y_axis()));
+        }
+        let handle = self.world.add_static(make_shared(body));
+        self.level.statics.push(handle);
+        Ok(())
+    }
+}
+
+/// Row of adjacent tiles entirely covered by their collision, built as a single body
+struct TileRun<'a> {
+    /// Id of the first tile of the row
+    id: u32,
+
+    /// Class of the tiles
+    class: &'a str,
+
+    /// Collision object of the first tile
+    object: &'a RawObject,
+
+    /// Cell of the first tile
+    cell: (usize, usize),
+
+    /// Number of tiles in the row
+    length: usize,
+
+    /// Properties shared by the tiles
+    properties: Vec<Property>,
+}
+
+impl TileRun<'_> {
+    /// Check if the tile in the given cell continues this row
+    fn extends(
+        &self,
+        cell: (usize, usize),
+        class: &str,
+        object: &RawObject,
+        properties: &[Property],
+    ) -> bool {
+        cell == (self.cell.0 + self.length, self.cell.1)
+            && class == self.class
+            && object.name == self.object.name
+            && properties == self.properties.as_slice()
+    }
+}
+
+/// Find the tile with the given id and its tileset
+fn find_tile(tilesets: &[RawTileset], id: u32) -> Option<(&RawTileset, &RawTile)> {
+    if id == 0 {
+        return None;
+    }
+    let tileset = tilesets
+        .iter()
+        .rev()
+        .find(|tileset| tileset.firstgid <= id)?;
+    let local = id - tileset.firstgid;
+    let tile = tileset.tiles.iter().find(|tile| tile.id == local)?;
+    Some((tileset, tile))
+}
+
+/// Concatenate the properties of a parent and a child
+fn inherit(parent: &[Property], child: &[Property]) -> Vec<Property> {
+    parent.iter().chain(child).cloned().collect()
+}
+
+/// Shape of an object in pixels, with the vertical axis pointing down
+enum Geometry {
+    /// Rectangle rotated around its center
+    Cuboid {
+        /// Center of the rectangle
+        center: Point<Real>,
+
+        /// Half of the size of the rectangle
+        half_extents: Vector<Real>,
+
+        /// Rotation of the rectangle in radians
+        angle: Real,
+    },
+
+    /// Circle
+    Ball {
+        /// Center of the circle
+        center: Point<Real>,
+
+        /// Radius of the circle
+        radius: Real,
+    },
+
+    /// Closed polygon, possibly concave
+    Polygon(Vec<Point<Real>>),
+
+    /// Open chain of segments
+    Polyline(Vec<Point<Real>>),
+}
+
+impl Geometry {
+    /// Read the shape of an object, points and texts have none
+    fn from_object(object: &RawObject) -> Option<Self> {
+        if object.point || object.text.is_some() || object.gid.is_some() {
+            return None;
+        }
+
+        // objects rotate clockwise around their origin
+        let (sin, cos) = ComplexField::sin_cos(object.rotation.to_radians());
+        let origin = Vector::new(object.x, object.y);
+        let place = |x: Real, y: Real| {
+            Point::from(origin + Vector::new(cos * x - sin * y, sin * x + cos * y))
+        };
+
+        if let Some(points) = object.polygon.as_ref() {
+            let points: Vec<_> = points.iter().map(|p| place(p.x, p.y)).collect();
+            return (points.len() >= 3).then_some(Self::Polygon(points));
+        }
+        if let Some(points) = object.polyline.as_ref() {
+            let points: Vec<_> = points.iter().map(|p| place(p.x, p.y)).collect();
+            return (points.len() >= 2).then_some(Self::Polyline(points));
+        }
+
+        let half_extents = Vector::new(object.width, object.height) * 0.5;
+        if half_extents.x <= 0.0 || half_extents.y <= 0.0 {
+            return None;
+        }
+        let center = place(half_extents.x, half_extents.y);
+
+        if !object.ellipse {
+            return Some(Self::Cuboid {
+                center,
+                half_extents,
+                angle: object.rotation.to_radians(),
+            });
+        }
+        if (half_extents.x - half_extents.y).abs() <= Real::EPSILON * half_extents.x {
+            return Some(Self::Ball {
+                center,
+                radius: half_extents.x,
+            });
+        }
+
+        // approximate the ellipses with polygons
+        let points = (0..ELLIPSE_POINTS)
+            .map(|index| {
+                let (sin, cos) =
+                    ComplexField::sin_cos(TAU * index as Real / ELLIPSE_POINTS as Real);
+                place(half_extents.x * (1.0 + cos), half_extents.y * (1.0 + sin))
+            })
+            .collect();
+        Some(Self::Polygon(points))
+    }
+
+    /// Move the shape by the given amount of pixels
+    fn translated(self, offset: Vector<Real>) -> Self {
+        self.transformed(|point| point + offset, false, false)
+    }
+
+    /// Apply the flip flags of a tile id to a shape drawn on a tile of the given size
+    fn flipped(self, gid: u32, size: Vector<Real>) -> Self {
+        let mut geometry = self;
+        let mut size = size;
+        if gid & FLIPPED_DIAGONALLY != 0 {
+            geometry = geometry.transformed(|point| Point::new(point.y, point.x), true, true);
+            size = Vector::new(size.y, size.x);
+        }
+        if gid & FLIPPED_HORIZONTALLY != 0 {
+            geometry =
+                geometry.transformed(|point| Point::new(size.x - point.x, point.y), true, false);
+        }
+        if gid & FLIPPED_VERTICALLY != 0 {
+            geometry =
+                geometry.transformed(|point| Point::new(point.x, size.y - point.y), true, false);
+        }
+        geometry
+    }
+
+    /// Transform the points of the shape.
+    /// Reflections invert the rotations, and may swap the axes of the rectangles.
+    fn transformed(
+        self,
+        transform: impl Fn(Point<Real>) -> Point<Real>,
+        reflects: bool,
+        swap_axes: bool,
+    ) -> Self {
+        match self {
+            Self::Cuboid {
+                center,
+                half_extents,
+                angle,
+            } => Self::Cuboid {
+                center: transform(center),
+                half_extents: if swap_axes {
+                    Vector::new(half_extents.y, half_extents.x)
+                } else {
+                    half_extents
+                },
+                angle: if reflects { -angle } else { angle },
+            },
+            Self::Ball { center, radius } => Self::Ball {
+                center: transform(center),
+                radius,
+            },
+            Self::Polygon(points) => Self::Polygon(points.into_iter().map(transform).collect()),
+            Self::Polyline(points) => Self::Polyline(points.into_iter().map(transform).collect()),
+        }
+    }
+
+    /// Find the collision type of the tile maps matching the shape drawn on a square tile
+    fn tile(&self, size: Real) -> Option<Tile> {
+        let points = match self {
+            Self::Cuboid {
+                center,
+                half_extents,
+                angle,
+            } if *angle == 0.0 => [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                .map(|(x, y)| center + Vector::new(half_extents.x * x, half_extents.y * y))
+                .to_vec(),
+            Self::Polygon(points) => points.clone(),
+            _ => return None,
+        };
+
+        // the outlines of the collision types start from the bottom of the cell
+        let points: Vec<_> = points
+            .into_iter()
+            .map(|point| Point::new(point.x, size - point.y))
+            .collect();
+        let tolerance = size * TILE_TOLERANCE;
+        let gentle = |corner| [true, false].map(|high| Tile::GentleSlope { corner, high });
+        [Tile::Solid]
+            .into_iter()
+            .chain(SIDES.map(Tile::Half))
+            .chain(CORNERS.map(Tile::Slope))
+            .chain(CORNERS.into_iter().flat_map(gentle))
+            .find(|tile| {
+                let outline = tile.outline(size);
+                outline.len() == points.len()
+                    && outline.iter().all(|corner| {
+                        points
+                            .iter()
+                            .any(|point| (point - corner).norm() <= tolerance)
+                    })
+            })
+    }
+
+    /// Build the shape in world units, with the vertical axis pointing up
+    fn build(self, scale: Real) -> Option<(Arc<dyn Shape>, Isometry<Real>)> {
+        let convert = |point: Point<Real>| Point::new(point.x * scale, -point.y * scale);
+        match self {
+            Self::Cuboid {
+                center,
+                half_extents,
+                angle,
+            } => Some((
+                Arc::new(Cuboid::new(half_extents * scale)),
+                Isometry::new(convert(center).coords, -angle),
+            )),
+            Self::Ball { center, radius } => Some((
+                Arc::new(Ball::new(radius * scale)),
+                Isometry::new(convert(center).coords, 0.0),
+            )),
+            Self::Polygon(points) => {
+                let (origin, points) = localize(points.into_iter().map(convert).collect());
+                Some((polygon(points)?, Isometry::new(origin, 0.0)))
+            }
+            Self::Polyline(points) => {
+                let (origin, points) = localize(points.into_iter().map(convert).collect());
+                Some((
+                    Arc::new(Polyline::new(points, None)),
+                    Isometry::new(origin, 0.0),
+                ))
+            }
+        }
+    }
+}
+
+/// Express the points relative to the first one
+fn localize(points: Vec<Point<Real>>) -> (Vector<Real>, Vec<Point<Real>>) {
+    let origin = points[0].coords;
+    let points = points.into_iter().map(|point| point - origin).collect();
+    (origin, points)
+}
+
+/// Build a polygon, concave polygons are split into convex parts
+fn polygon(mut points: Vec<Point<Real>>) -> Option<Arc<dyn Shape>> {
+    // the points must go counter-clockwise
+    let area: Real = (0..points.len())
+        .map(|index| {
+            let (a, b) = (points[index], points[(index + 1) % points.len()]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum();
+    if area < 0.0 {
+        points.reverse();
+    }
+
+    let convex = (0..points.len()).all(|index| {
+        let a = points[index];
+        let b = points[(index + 1) % points.len()];
+        let c = points[(index + 2) % points.len()];
+        (b - a).perp(&(c - b)) >= 0.0
+    });
+    if convex {
+        return SharedShape::convex_polyline(points).map(|shape| shape.0);
+    }
+
+    let mesh = TriMesh::from_polygon(points)?;
+    let parts: Vec<_> = hertel_mehlhorn(mesh.vertices(), mesh.indices())
+        .into_iter()
+        .filter_map(SharedShape::convex_polyline)
+        .map(|part| (Isometry::identity(), part))
+        .collect();
+    (!parts.is_empty()).then(|| Arc::new(Compound::new(parts)) as Arc<dyn Shape>)
+}
+
+/// Root of a map
+#[derive(Deserialize)]
+struct RawMap {
+    /// Layout of the cells
+    #[serde(default)]
+    orientation: String,
+
+    /// Specify if the map is split into chunks
+    #[serde(default)]
+    infinite: bool,
+
+    /// Width of the cells in pixels
+    tilewidth: u32,
+
+    /// Height of the cells in pixels
+    tileheight: u32,
+
+    /// Layers of the map, from bottom to top
+    #[serde(default)]
+    layers: Vec<RawLayer>,
+
+    /// Tilesets used by the map
+    #[serde(default)]
+    tilesets: Vec<RawTileset>,
+}
+
+/// Layer of any type
+#[derive(Deserialize)]
+struct RawLayer {
+    /// Type of the layer
+    #[serde(rename = "type")]
+    kind: String,
+
+    /// Unique identifier
+    #[serde(default)]
+    id: u32,
+
+    /// Name of the layer
+    #[serde(default)]
+    name: String,
+
+    /// Class of the layer
+    #[serde(default)]
+    class: String,
+
+    /// Horizontal offset in pixels
+    #[serde(default)]
+    offsetx: Real,
+
+    /// Vertical offset in pixels
+    #[serde(default)]
+    offsety: Real,
+
+    /// Custom properties
+    #[serde(default)]
+    properties: Vec<Property>,
+
+    /// Number of columns of a tile layer
+    #[serde(default)]
+    width: usize,
+
+    /// Tile ids of a tile layer, row by row from the top
+    #[serde(default)]
+    data: Option<RawData>,
+
+    /// Objects of an object layer
+    #[serde(default)]
+    objects: Vec<RawObject>,
+
+    /// Children of a group layer
+    #[serde(default)]
+    layers: Vec<RawLayer>,
+}
+
+/// Tile ids of a tile layer
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawData {
+    /// Plain list of tile ids
+    Array(Vec<u32>),
+
+    /// Tile ids encoded in base64 and possibly compressed
+    Encoded(IgnoredAny),
+}
+
+/// Object of an object layer or of the collision of a tile
+#[derive(Deserialize)]
+struct RawObject {
+    /// Unique identifier
+    #[serde(default)]
+    id: u32,
+
+    /// Name of the object
+    #[serde(default)]
+    name: String,
+
+    /// Class of the object
+    #[serde(default, rename = "type", alias = "class")]
+    class: String,
+
+    /// Horizontal position in pixels
+    #[serde(default)]
+    x: Real,
+
+    /// Vertical position in pixels
+    #[serde(default)]
+    y: Real,
+
+    /// Width in pixels
+    #[serde(default)]
+    width: Real,
+
+    /// Height in pixels
+    #[serde(default)]
+    height: Real,
+
+    /// Clockwise rotation in degrees
+    #[serde(default)]
+    rotation: Real,
+
+    /// Specify if the object is an ellipse
+    #[serde(default)]
+    ellipse: bool,
+
+    /// Specify if the object is a point
+    #[serde(default)]
+    point: bool,
+
+    /// Points of a polygon, relative to the position
+    #[serde(default)]
+    polygon: Option<Vec<RawPoint>>,
+
+    /// Points of a polyline, relative to the position
+    #[serde(default)]
+    polyline: Option<Vec<RawPoint>>,
+
+    /// Tile id of a tile object
+    #[serde(default)]
+    gid: Option<u32>,
+
+    /// Content of a text object
+    #[serde(default)]
+    text: Option<Value>,
+
+    /// Custom properties
+    #[serde(default)]
+    properties: Vec<Property>,
+}
+
+impl RawObject {
+    /// Check if the object is a rectangle covering a whole tile of the given size
+    fn fills(&self, size: Vector<Real>) -> bool {
+        matches!(Geometry::from_object(self), Some(Geometry::Cuboid { .. }))
+            && self.rotation == 0.0
+            && self.x == 0.0
+            && self.y == 0.0
+            && self.width == size.x
+            && self.height == size.y
+    }
+}
+
+/// Point of a polygon or a polyline
+#[derive(Deserialize)]
+struct RawPoint {
+    /// Horizontal position in pixels
+    x: Real,
+
+    /// Vertical position in pixels
+    y: Real,
+}
+
+/// Tileset embedded in a map or saved in its own file
+#[derive(Deserialize)]
+struct RawTileset {
+    /// Id of the first tile of the set in the map
+    #[serde(default)]
+    firstgid: u32,
+
+    /// Path of an external tileset
+    #[serde(default)]
+    source: Option<String>,
+
+    /// Width of the tiles in pixels
+    #[serde(default)]
+    tilewidth: u32,
+
+    /// Height of the tiles in pixels
+    #[serde(default)]
+    tileheight: u32,
+
+    /// Tiles with custom data
+    #[serde(default)]
+    tiles: Vec<RawTile>,
+}
+
+/// Tile with custom data
+#[derive(Deserialize)]
+struct RawTile {
+    /// Id of the tile in its tileset
+    id: u32,
+
+    /// Class of the tile
+    #[serde(default, rename = "type", alias = "class")]
+    class: String,
+
+    /// Collision drawn on the tile
+    #[serde(default)]
+    objectgroup: Option<RawObjectGroup>,
+
+    /// Custom properties
+    #[serde(default)]
+    properties: Vec<Property>,
+}
+
+/// Collision drawn on a tile
+#[derive(Deserialize)]
+struct RawObjectGroup {
+    /// Shapes of the collision
+    #[serde(default)]
+    objects: Vec<RawObject>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PayloadFactory, TiledError, TiledLevel, TiledLoader, TiledObject};
+    use crate::{
+        object::{
+            tile_map::{Corner, Tile},
+            trigger_area::OnOverlap,
+            Object,
+        },
+        world::World,
+        Mask,
+    };
+    use alloc::{format, string::String, vec::Vec};
+    use parry::{
+        bounding_volume::Aabb,
+        math::{Point, Vector},
+    };
+
+    /// Tiles of 16 pixels: a solid block, a slope, a one-way block,
+    /// a block on the layer 4 and a circle
+    const TILESET: &str = r#"{
+        "firstgid": 1, "tilewidth": 16, "tileheight": 16,
+        "tiles": [
+            {"id": 0, "objectgroup": {"objects": [
+                {"x": 0, "y": 0, "width": 16, "height": 16}
+            ]}},
+            {"id": 1, "objectgroup": {"objects": [
+                {"x": 0, "y": 0, "polygon": [{"x": 0, "y": 16}, {"x": 16, "y": 16}, {"x": 16, "y": 0}]}
+            ]}},
+            {"id": 2, "properties": [{"name": "one_way", "type": "bool", "value": true}],
+             "objectgroup": {"objects": [
+                {"x": 0, "y": 0, "width": 16, "height": 16}
+            ]}},
+            {"id": 3, "properties": [{"name": "layer", "type": "int", "value": 4}],
+             "objectgroup": {"objects": [
+                {"x": 0, "y": 0, "width": 16, "height": 16}
+            ]}},
+            {"id": 4, "objectgroup": {"objects": [
+                {"x": 4, "y": 4, "width": 8, "height": 8, "ellipse": true}
+            ]}}
+        ]
+    }"#;
+
+    /// Payloads of the tests, the triggers do nothing
+    struct Factory;
+
+    impl PayloadFactory<(), ()> for Factory {
+        fn body(&mut self, _: &TiledObject) {}
+
+        fn trigger(&mut self, _: &TiledObject) -> ((), OnOverlap<(), ()>) {
+            ((), |_, _, _| {})
+        }
+    }
+
+    /// Map made of the given layers, using the test tileset
+    fn map(layers: &str) -> String {
+        format!(
+            r#"{{"orientation": "orthogonal", "tilewidth": 16, "tileheight": 16,
+                "tilesets": [{TILESET}], "layers": [{layers}]}}"#
+        )
+    }
+
+    /// Tile layer with 3 columns
+    fn tiles(data: &str, properties: &str) -> String {
+        format!(
+            r#"{{"type": "tilelayer", "name": "tiles", "width": 3, "height": 2,
+                "data": [{data}], "properties": [{properties}]}}"#
+        )
+    }
+
+    /// Load a map with a scale of one unit per tile
+    fn load(world: &mut World, json: &str) -> Result<TiledLevel, TiledError> {
+        TiledLoader::new(1.0 / 16.0).load(world, json, &mut Factory)
+    }
+
+    /// Bounds of a static body of the world
+    fn bounds(world: &World, level: &TiledLevel, index: usize) -> Aabb {
+        let body = world.get_static(level.statics[index]).unwrap().read();
+        body.shape().compute_aabb(body.isometry())
+    }
+
+    #[test]
+    fn tile_layers_become_tile_maps() {
+        let mut world = World::new(0.001);
+        let level = load(&mut world, &map(&tiles("1, 1, 2, 2147483650, 1, 0", ""))).unwrap();
+        assert!(level.statics.is_empty());
+        assert_eq!(level.tile_maps.len(), 1);
+
+        // the top row of the layer is the highest row of the map
+        let map = world.get_tile_map(level.tile_maps[0]).unwrap().read();
+        assert_eq!(*map.origin(), Vector::new(0.0, -2.0));
+        assert_eq!(map.tile_size(), 1.0);
+        assert_eq!((map.width(), map.height()), (3, 2));
+        assert_eq!(map.layer(), Mask::MAX);
+        assert_eq!(map.tile((0, 1)), Tile::Solid);
+        assert_eq!(map.tile((1, 1)), Tile::Solid);
+        assert_eq!(map.tile((2, 1)), Tile::Slope(Corner::BottomRight));
+        assert_eq!(map.tile((0, 0)), Tile::Slope(Corner::BottomLeft));
+        assert_eq!(map.tile((1, 0)), Tile::Solid);
+        assert_eq!(map.tile((2, 0)), Tile::Empty);
+    }
+
+    #[test]
+    fn one_way_tiles_merge_into_rows() {
+        let mut world = World::new(0.001);
+        let level = load(&mut world, &map(&tiles("3, 3, 3, 1, 3, 0", ""))).unwrap();
+        assert_eq!(level.tile_maps.len(), 1);
+        assert_eq!(level.statics.len(), 2);
+
+        let row = bounds(&world, &level, 0);
+        assert!((row.mins - Point::new(0.0, -1.0)).norm() < 1e-5);
+        assert!((row.maxs - Point::new(3.0, 0.0)).norm() < 1e-5);
+        let single = bounds(&world, &level, 1);
+        assert!((single.mins - Point::new(1.0, -2.0)).norm() < 1e-5);
+        assert!((single.maxs - Point::new(2.0, -1.0)).norm() < 1e-5);
+
+        for handle in level.statics.iter() {
+            let body = world.get_static(*handle).unwrap().read();
+            assert_eq!(body.one_way(), Some(&Vector::y_axis()));
+        }
+
+        // the map only keeps the tiles blocking from every side
+        let map = world.get_tile_map(level.tile_maps[0]).unwrap().read();
+        assert_eq!(map.tile((0, 0)), Tile::Solid);
+        assert_eq!(map.tile((1, 0)), Tile::Empty);
+    }
+
+    #[test]
+    fn properties_define_the_masks() {
+        let objects = r#"{"type": "objectgroup", "name": "objects",
+            "properties": [{"name": "layer", "type": "int", "value": 8}],
+            "objects": [
+                {"id": 1, "x": 0, "y": 0, "width": 16, "height": 16},
+                {"id": 2, "x": 0, "y": 0, "width": 16, "height": 16, "properties": [
+                    {"name": "trigger", "type": "bool", "value": true},
+                    {"name": "mask", "type": "int", "value": 16}
+                ]}
+            ]}"#;
+        let layer = tiles(
+            "1, 4, 5, 0, 0, 0",
+            r#"{"name": "layer", "type": "int", "value": 2}"#,
+        );
+        let mut world = World::new(0.001);
+        let level = load(&mut world, &map(&format!("{layer}, {objects}"))).unwrap();
+
+        // tiles on other layers than the map and unknown collision become static bodies
+        let map = world.get_tile_map(level.tile_maps[0]).unwrap().read();
+        assert_eq!(map.layer(), 2);
+        let layers: Vec<_> = level
+            .statics
+            .iter()
+            .map(|handle| world.get_static(*handle).unwrap().read().layer())
+            .collect();
+        assert_eq!(layers, [2, 4, 8]);
+
+        let trigger = world.get_trigger(level.triggers[0]).unwrap().read();
+        assert_eq!(trigger.mask(), 16);
+    }
+
+    #[test]
+    fn invalid_properties_are_rejected() {
+        let invalid = [
+            (
+                tiles("1, 0, 0, 0, 0, 0", r#"{"name": "layer", "value": -1}"#),
+                "layer",
+            ),
+            (
+                tiles("1, 0, 0, 0, 0, 0", r#"{"name": "layer", "value": 1.5}"#),
+                "layer",
+            ),
+            (
+                String::from(
+                    r#"{"type": "objectgroup", "objects": [
+                        {"x": 0, "y": 0, "width": 16, "height": 16, "properties": [
+                            {"name": "trigger", "value": true},
+                            {"name": "mask", "value": "all"}
+                        ]}
+                    ]}"#,
+                ),
+                "mask",
+            ),
+        ];
+        for (layer, property) in invalid {
+            let mut world = World::new(0.001);
+            match load(&mut world, &map(&layer)) {
+                Err(TiledError::InvalidProperty(name)) => assert_eq!(name, property),
+                _ => panic!("the property {property} should be rejected"),
+            }
+        }
+    }
+}
@@ -19,6 +19,10 @@ pub mod object;
 /// Define the world
 pub mod world;
 
+/// Import collision from external formats
+#[cfg(any(feature = "tiled", feature = "obj", feature = "gltf"))]
+pub mod import;
+
 /// Use alloc crate for no_std support
 extern crate alloc;
 
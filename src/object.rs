@@ -9,41 +9,54 @@ pub mod static_body;
 /// Trigger area
 pub mod trigger_area;
 
+/// Moving platform
+pub mod moving_platform;
+
 /// Hit result between solid objects
 pub mod contact;
 
+/// Character controller
+pub mod character;
+
+/// Grid of tiles
+#[cfg(feature = "2d")]
+pub mod tile_map;
+
+/// Serializable shapes
+#[cfg(feature = "serde")]
+pub mod shape;
+
 use super::Mask;
 use crate::{
     object::{kinematic_body::KinematicBody, static_body::StaticBody},
     world::aabb::Aabb,
 };
 use alloc::sync::Arc;
-use bvh_arena::VolumeHandle;
 use parry::{
     math::{Isometry, Real, Vector},
     query::{self, Contact, ShapeCastHit, ShapeCastOptions},
     shape::Shape,
 };
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 /// Mask where all bits are set to 1
 const MASK_ALL: Mask = Mask::MAX;
 
+/// Default maximum angle between the up vector and a surface a body can stand on
+#[allow(clippy::unnecessary_cast)]
+const DEFAULT_FLOOR_ANGLE: Real = core::f32::consts::FRAC_PI_4 as Real;
+
 /// Trait implemented for static and dynamic bodies
 pub trait Object {
     type Payload;
 
-    /// Store the handle of this object after it has been added to the world
-    fn set_handle(&mut self, handle: VolumeHandle);
-
-    /// Unset the handle of this object
-    fn unset_handle(&mut self);
-
-    /// Access the handle of this object
-    fn handle(&self) -> Option<VolumeHandle>;
-
     /// Access the shape assigned to this body
     fn shape(&self) -> &dyn Shape;
 
+    /// Access the shared reference to the shape assigned to this body
+    fn shared_shape(&self) -> &Arc<dyn Shape>;
+
     /// Access the isometry of this shape
     fn isometry(&self) -> &Isometry<Real>;
 
@@ -87,12 +100,12 @@ pub trait Object {
     }
 }
 
-/// Common data shared between static and dynamic bodies
+/// Common data shared between static and dynamic bodies.
+/// The shape is saved apart, in a table shared by the objects of a world.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 struct CommonData<P> {
-    /// Handle of this body in the world
-    handle: Option<VolumeHandle>,
-
     /// Collision shape used by this zone
+    #[cfg_attr(feature = "serde", serde(skip, default = "shape::placeholder"))]
     shape: Arc<dyn Shape>,
 
     /// Isometry of this body
@@ -107,7 +120,6 @@ impl<P> CommonData<P> {
     #[inline]
     pub fn new(shape: Arc<dyn Shape>, isometry: Isometry<Real>, payload: P) -> Self {
         CommonData {
-            handle: None,
             shape,
             isometry,
             payload,
@@ -115,26 +127,16 @@ impl<P> CommonData<P> {
     }
 }
 
-impl<P> Object for CommonData<P> {
-    type Payload = P;
-
-    /// Store the handle of this object after it has been added to the world
-    #[inline]
-    fn set_handle(&mut self, handle: VolumeHandle) {
-        self.handle = Some(handle);
-    }
-
-    /// Unset the handle of this object
+#[cfg(feature = "serde")]
+impl<P> shape::SavedShape for CommonData<P> {
     #[inline]
-    fn unset_handle(&mut self) {
-        self.handle = None;
+    fn restore_shape(&mut self, shape: Arc<dyn Shape>) {
+        self.shape = shape;
     }
+}
 
-    /// Access the handle of this object
-    #[inline]
-    fn handle(&self) -> Option<VolumeHandle> {
-        self.handle
-    }
+impl<P> Object for CommonData<P> {
+    type Payload = P;
 
     /// Access the shape assigned to this body
     #[inline]
@@ -142,9 +144,15 @@ impl<P> Object for CommonData<P> {
         self.shape.as_ref()
     }
 
+    /// Access the shared reference to the shape assigned to this body
+    #[inline]
+    fn shared_shape(&self) -> &Arc<dyn Shape> {
+        &self.shape
+    }
+
     /// Access the isometry of this shape
     #[inline]
-    fn isometry(&self) -> &Isometry<f32> {
+    fn isometry(&self) -> &Isometry<Real> {
         &self.isometry
     }
 
@@ -194,7 +202,7 @@ where
     A: Object,
     B: Object,
 {
-    query::cast_shapes(
+    cast_shapes(
         a.isometry(),
         &a.velocity(),
         a.shape(),
@@ -203,5 +211,26 @@ where
         b.shape(),
         options,
     )
+}
+
+/// Compute the time of impact between two moving shapes
+pub(crate) fn cast_shapes(
+    isometry1: &Isometry<Real>,
+    velocity1: &Vector<Real>,
+    shape1: &dyn Shape,
+    isometry2: &Isometry<Real>,
+    velocity2: &Vector<Real>,
+    shape2: &dyn Shape,
+    options: ShapeCastOptions,
+) -> Option<ShapeCastHit> {
+    query::cast_shapes(
+        isometry1, velocity1, shape1, isometry2, velocity2, shape2, options,
+    )
     .unwrap_or(None)
 }
+
+/// Normal of a hit on the first shape, rotated from its local space into world space
+#[inline]
+pub(crate) fn world_normal(isometry1: &Isometry<Real>, hit: &ShapeCastHit) -> Vector<Real> {
+    isometry1.rotation * hit.normal1.into_inner()
+}
@@ -0,0 +1,150 @@
+//! Character controller sliding kinematic bodies along surfaces
+
+use super::DEFAULT_FLOOR_ANGLE;
+use parry::{
+    math::{Real, Vector},
+    na::{ComplexField, Unit},
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Default maximum number of slides per tick
+pub(crate) const DEFAULT_MAX_SLIDES: u32 = 4;
+
+/// Character controller moving a kinematic body by sliding along the surfaces it hits
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CharacterController {
+    /// Direction pointing away from the floor
+    up: Unit<Vector<Real>>,
+
+    /// Cosine of the maximum angle between the up vector and a walkable surface
+    min_floor_dot: Real,
+
+    /// Maximum number of slides per tick
+    max_slides: u32,
+
+    /// Specify if the body touched the floor on the last tick
+    on_floor: bool,
+
+    /// Specify if the body touched a wall on the last tick
+    on_wall: bool,
+
+    /// Specify if the body touched the ceiling on the last tick
+    on_ceiling: bool,
+
+    /// Normal of the floor touched on the last tick
+    floor_normal: Vector<Real>,
+}
+
+impl CharacterController {
+    /// Create a new character controller
+    #[inline]
+    pub fn new(up: Unit<Vector<Real>>, floor_angle: Real, max_slides: u32) -> Self {
+        Self {
+            up,
+            min_floor_dot: ComplexField::cos(floor_angle),
+            max_slides,
+            on_floor: false,
+            on_wall: false,
+            on_ceiling: false,
+            floor_normal: Vector::zeros(),
+        }
+    }
+
+    /// Direction pointing away from the floor
+    #[inline]
+    pub fn up(&self) -> &Unit<Vector<Real>> {
+        &self.up
+    }
+
+    /// Change the direction pointing away from the floor
+    #[inline]
+    pub fn set_up(&mut self, up: Unit<Vector<Real>>) {
+        self.up = up;
+    }
+
+    /// Change the maximum angle between the up vector and a walkable surface
+    #[inline]
+    pub fn set_floor_angle(&mut self, angle: Real) {
+        self.min_floor_dot = ComplexField::cos(angle);
+    }
+
+    /// Maximum number of slides per tick
+    #[inline]
+    pub fn max_slides(&self) -> u32 {
+        self.max_slides
+    }
+
+    /// Change the maximum number of slides per tick
+    #[inline]
+    pub fn set_max_slides(&mut self, max_slides: u32) {
+        self.max_slides = max_slides;
+    }
+
+    /// Check if the body touched the floor on the last tick
+    #[inline]
+    pub fn is_on_floor(&self) -> bool {
+        self.on_floor
+    }
+
+    /// Check if the body touched a wall on the last tick
+    #[inline]
+    pub fn is_on_wall(&self) -> bool {
+        self.on_wall
+    }
+
+    /// Check if the body touched the ceiling on the last tick
+    #[inline]
+    pub fn is_on_ceiling(&self) -> bool {
+        self.on_ceiling
+    }
+
+    /// Normal of the floor touched on the last tick
+    #[inline]
+    pub fn floor_normal(&self) -> Option<Vector<Real>> {
+        self.on_floor.then_some(self.floor_normal)
+    }
+
+    /// Check if a surface with the given normal is walkable
+    #[inline]
+    pub fn is_floor(&self, normal: &Vector<Real>) -> bool {
+        normal.dot(&self.up) >= self.min_floor_dot
+    }
+
+    /// Forget about the surfaces touched on the last tick
+    #[inline]
+    pub(crate) fn reset(&mut self) {
+        self.on_floor = false;
+        self.on_wall = false;
+        self.on_ceiling = false;
+        self.floor_normal = Vector::zeros();
+    }
+
+    /// Register the floor the body landed on after climbing a step
+    #[inline]
+    pub(crate) fn land(&mut self, normal: &Vector<Real>) {
+        self.on_floor = true;
+        self.on_wall = false;
+        self.floor_normal = *normal;
+    }
+
+    /// Classify the surface touched given its normal
+    pub(crate) fn touch(&mut self, normal: &Vector<Real>) {
+        let dot = normal.dot(&self.up);
+        if dot >= self.min_floor_dot {
+            self.on_floor = true;
+            self.floor_normal = *normal;
+        } else if dot <= -self.min_floor_dot {
+            self.on_ceiling = true;
+        } else {
+            self.on_wall = true;
+        }
+    }
+}
+
+impl Default for CharacterController {
+    fn default() -> Self {
+        Self::new(Vector::y_axis(), DEFAULT_FLOOR_ANGLE, DEFAULT_MAX_SLIDES)
+    }
+}
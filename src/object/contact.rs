@@ -1,7 +1,12 @@
 //! Contact between two solid objects
 
+use alloc::sync::Arc;
 use core::cmp::Ordering;
-use parry::{math::Real, query::ShapeCastHit};
+use parry::{
+    math::{Isometry, Real, Vector},
+    query::ShapeCastHit,
+    shape::Shape,
+};
 
 /// Contact data
 pub struct Contact<P = ()> {
@@ -13,16 +18,35 @@ pub struct Contact<P = ()> {
 
     /// Payload of the other object
     payload: P,
+
+    /// Shape of the other object
+    shape: Arc<dyn Shape>,
+
+    /// Isometry of the other object when the contact was found
+    isometry: Isometry<Real>,
+
+    /// Velocity of the other object when the contact was found
+    velocity: Vector<Real>,
 }
 
 impl<P> Contact<P> {
     /// Create a new contact result
     #[inline]
-    pub fn new(hit: ShapeCastHit, weight_ratio: Real, payload: P) -> Self {
+    pub fn new(
+        hit: ShapeCastHit,
+        weight_ratio: Real,
+        payload: P,
+        shape: Arc<dyn Shape>,
+        isometry: Isometry<Real>,
+        velocity: Vector<Real>,
+    ) -> Self {
         Self {
             hit,
             weight_ratio,
             payload,
+            shape,
+            isometry,
+            velocity,
         }
     }
 
@@ -51,6 +75,24 @@ impl<P> Contact<P> {
         &mut self.payload
     }
 
+    /// Access the shape of the other object
+    #[inline]
+    pub fn shape(&self) -> &dyn Shape {
+        self.shape.as_ref()
+    }
+
+    /// Access the isometry of the other object
+    #[inline]
+    pub fn isometry(&self) -> &Isometry<Real> {
+        &self.isometry
+    }
+
+    /// Get the velocity of the other object
+    #[inline]
+    pub fn velocity(&self) -> Vector<Real> {
+        self.velocity
+    }
+
     /// Compare two contact results to order them from nearest to furtherest
     pub fn order(&self, other: &Self, epsilon: Real) -> Ordering {
         let ta = self.hit.time_of_impact;
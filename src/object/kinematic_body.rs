@@ -1,18 +1,26 @@
 //! Kinematic body which reports collisions
 
-use super::{CommonData, Mask, Object};
-use crate::{object::contact::Contact, world::aabb::Aabb};
+#[cfg(feature = "serde")]
+use super::shape::SavedShape;
+use super::{cast_shapes, world_normal, CommonData, Mask, Object};
+use crate::{
+    object::{character::CharacterController, contact::Contact},
+    world::aabb::Aabb,
+};
 use alloc::{boxed::Box, sync::Arc, vec::Vec};
-use bvh_arena::VolumeHandle;
 use delegate::delegate;
 use nalgebra_glm::is_null;
 use parry::{
     math::{Isometry, Real, Translation, Vector},
-    query::ShapeCastHit,
+    na::Unit,
+    query::{ShapeCastHit, ShapeCastOptions},
     shape::Shape,
 };
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 /// A kinematic body in the world
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct KinematicBody<P = ()> {
     /// Shape, isometry and handle
     common: CommonData<P>,
@@ -36,9 +44,41 @@ pub struct KinematicBody<P = ()> {
     /// Target isometry at the next tick
     next_isometry: Isometry<Real>,
 
+    /// Displacement of the platform carrying this body over the current tick
+    carry: Vector<Real>,
+
+    /// Character controller sliding this body along surfaces
+    controller: Option<CharacterController>,
+
+    /// Maximum height of the obstacles this body can step on
+    step_height: Real,
+
+    /// Motion the body intended to do over the current tick
+    desired_motion: Vector<Real>,
+
+    /// Time left during which the body passes through one-way bodies
+    drop_time: Real,
+
+    /// Maximum distance the body can be pulled down to stay on the ground
+    snap_distance: Real,
+
+    /// Specify if the body stands on the floor after the current tick
+    on_floor: bool,
+
+    /// Specify if the body stood on the floor after the previous tick
+    was_on_floor: bool,
+
+    /// Specify if the body is sleeping and skipped by the updates
+    sleeping: bool,
+
+    /// Number of consecutive ticks the body stayed at rest
+    idle_ticks: u32,
+
     /// Store collision results
-    /// Hit results are stored in boxes so that reordoring the vector can be quicker
+    /// Hit results are stored in boxes so that reordoring the vector can be quicker.
+    /// They are found again on each update, so they are not saved.
     #[allow(clippy::vec_box)]
+    #[cfg_attr(feature = "serde", serde(skip, default = "Vec::new"))]
     contacts: Vec<Box<Contact<P>>>,
 }
 
@@ -61,20 +101,36 @@ impl<P> KinematicBody<P> {
             bounce,
             velocity: Vector::zeros(),
             next_isometry: isometry,
+            carry: Vector::zeros(),
+            controller: None,
+            step_height: 0.0,
+            desired_motion: Vector::zeros(),
+            drop_time: 0.0,
+            snap_distance: 0.0,
+            on_floor: false,
+            was_on_floor: false,
+            sleeping: false,
+            idle_ticks: 0,
             contacts: Vec::new(),
         }
     }
 }
 
+#[cfg(feature = "serde")]
+impl<P> SavedShape for KinematicBody<P> {
+    #[inline]
+    fn restore_shape(&mut self, shape: Arc<dyn Shape>) {
+        self.common.restore_shape(shape);
+    }
+}
+
 impl<P> Object for KinematicBody<P> {
     type Payload = P;
 
     delegate! {
         to self.common {
-            #[inline] fn set_handle(&mut self, handle: VolumeHandle);
-            #[inline] fn unset_handle(&mut self);
-            #[inline] fn handle(&self) -> Option<VolumeHandle>;
             #[inline] fn shape(&self) -> &dyn Shape;
+            #[inline] fn shared_shape(&self) -> &Arc<dyn Shape>;
             #[inline] fn isometry(&self) -> &Isometry<Real>;
             #[inline] fn payload(&self) -> &P;
             #[inline] fn payload_mut(&mut self) -> &mut P;
@@ -129,8 +185,75 @@ impl<P> KinematicBody<P> {
         let translation = Translation::from(self.velocity * delta_time);
         self.next_isometry.append_translation_mut(&translation);
 
+        // Remember where the body wanted to go before any collision
+        self.desired_motion =
+            self.next_isometry.translation.vector - self.common.isometry.translation.vector;
+
         // Reset the list of hits
         self.contacts.clear();
+        self.carry = Vector::zeros();
+
+        // Count down the time left to drop through one-way bodies
+        self.drop_time = (self.drop_time - delta_time).max(0.0);
+
+        // Remember if the body stood on the floor before moving
+        self.was_on_floor = core::mem::take(&mut self.on_floor);
+    }
+
+    /// Keep a sleeping body in place for the current tick
+    pub(crate) fn rest(&mut self) {
+        self.common.isometry = self.next_isometry;
+        self.velocity = Vector::zeros();
+        self.desired_motion = Vector::zeros();
+        self.contacts.clear();
+        self.carry = Vector::zeros();
+    }
+
+    /// Count the ticks spent at rest and put the body to sleep after enough of them.
+    /// A count of zero ticks disables sleeping.
+    pub(crate) fn update_sleep(&mut self, threshold: Real, ticks: u32, delta_time: Real) {
+        if ticks == 0 {
+            return;
+        }
+
+        let motion =
+            self.next_isometry.translation.vector - self.common.isometry.translation.vector;
+        if self.velocity.norm() <= threshold && motion.norm() <= threshold * delta_time {
+            self.idle_ticks += 1;
+            if self.idle_ticks >= ticks {
+                self.sleep();
+            }
+        } else {
+            self.idle_ticks = 0;
+        }
+    }
+
+    /// Check if the body is sleeping
+    #[inline]
+    pub fn is_sleeping(&self) -> bool {
+        self.sleeping
+    }
+
+    /// Put the body to sleep, it stays in place until woken up.
+    /// The body wakes up on the next update if its velocity is raised above the sleep threshold.
+    #[inline]
+    pub fn sleep(&mut self) {
+        self.sleeping = true;
+        self.velocity = Vector::zeros();
+    }
+
+    /// Wake the body up
+    #[inline]
+    pub fn wake_up(&mut self) {
+        self.sleeping = false;
+        self.idle_ticks = 0;
+    }
+
+    /// Change the velocity of the body and wake it up
+    #[inline]
+    pub fn set_velocity(&mut self, velocity: Vector<Real>) {
+        self.velocity = velocity;
+        self.wake_up();
     }
 
     /// Access the weight of the kinematic body
@@ -145,9 +268,167 @@ impl<P> KinematicBody<P> {
         &self.next_isometry
     }
 
+    /// Access the character controller of this body
+    #[inline]
+    pub fn controller(&self) -> Option<&CharacterController> {
+        self.controller.as_ref()
+    }
+
+    /// Mutable access the character controller of this body
+    #[inline]
+    pub fn controller_mut(&mut self) -> Option<&mut CharacterController> {
+        self.controller.as_mut()
+    }
+
+    /// Change the character controller of this body.
+    /// A body with a controller slides along surfaces instead of being pushed back.
+    #[inline]
+    pub fn set_controller(&mut self, controller: Option<CharacterController>) {
+        self.controller = controller;
+    }
+
+    /// Maximum height of the obstacles this body can step on
+    #[inline]
+    pub fn step_height(&self) -> Real {
+        self.step_height
+    }
+
+    /// Change the maximum height of the obstacles this body can step on.
+    /// A height of zero disables stepping.
+    #[inline]
+    pub fn set_step_height(&mut self, step_height: Real) {
+        self.step_height = step_height;
+    }
+
+    /// Let the body pass through one-way bodies for the given duration
+    #[inline]
+    pub fn drop_through(&mut self, duration: Real) {
+        self.drop_time = duration;
+    }
+
+    /// Check if the body currently passes through one-way bodies
+    #[inline]
+    pub fn is_dropping(&self) -> bool {
+        self.drop_time > 0.0
+    }
+
+    /// Motion the body intended to do over the current tick
+    #[inline]
+    pub fn desired_motion(&self) -> &Vector<Real> {
+        &self.desired_motion
+    }
+
+    /// Direction pointing away from the floor for this body
+    #[inline]
+    pub fn up(&self) -> Unit<Vector<Real>> {
+        self.controller
+            .as_ref()
+            .map_or_else(Vector::y_axis, |controller| *controller.up())
+    }
+
+    /// Maximum distance the body can be pulled down to stay on the ground
+    #[inline]
+    pub fn snap_distance(&self) -> Real {
+        self.snap_distance
+    }
+
+    /// Change the maximum distance the body can be pulled down to stay on the ground.
+    /// A distance of zero disables snapping.
+    #[inline]
+    pub fn set_snap_distance(&mut self, snap_distance: Real) {
+        self.snap_distance = snap_distance;
+    }
+
+    /// Check if the body stands on the floor after the current tick
+    #[inline]
+    pub fn is_on_floor(&self) -> bool {
+        self.on_floor
+    }
+
+    /// Check if the body stood on the floor after the previous tick
+    #[inline]
+    pub fn was_on_floor(&self) -> bool {
+        self.was_on_floor
+    }
+
+    /// Check if a surface with the given normal is walkable for this body
+    #[inline]
+    pub fn is_floor(&self, normal: &Vector<Real>) -> bool {
+        self.controller.unwrap_or_default().is_floor(normal)
+    }
+
+    /// Move the next isometry of the body to the given translation
+    fn set_next_translation(&mut self, translation: Vector<Real>) {
+        self.next_isometry.translation.vector = translation;
+    }
+
+    /// Place the body on the ground it was pulled down to
+    pub(crate) fn snap(&mut self, translation: Vector<Real>, floor_normal: &Vector<Real>) {
+        self.set_next_translation(translation);
+        self.on_floor = true;
+        if let Some(controller) = self.controller.as_mut() {
+            controller.land(floor_normal);
+        }
+    }
+
+    /// Place the body on top of a step it climbed
+    pub(crate) fn climb(
+        &mut self,
+        translation: Vector<Real>,
+        floor_normal: &Vector<Real>,
+        delta_time: Real,
+    ) {
+        self.set_next_translation(translation);
+        self.on_floor = true;
+
+        // restore the motion lost against the step but stop falling
+        let up = self.up();
+        let lateral = self.desired_motion - up.into_inner() * self.desired_motion.dot(&up);
+        let vertical = self.velocity.dot(&up).max(0.0);
+        self.velocity = lateral / delta_time + up.into_inner() * vertical;
+
+        if let Some(controller) = self.controller.as_mut() {
+            controller.land(floor_normal);
+        }
+    }
+
+    /// Place the body where its character controller slid it
+    pub(crate) fn slide(
+        &mut self,
+        translation: Vector<Real>,
+        velocity: Vector<Real>,
+        controller: CharacterController,
+    ) {
+        self.set_next_translation(translation);
+        self.velocity = velocity;
+        self.on_floor = controller.is_on_floor();
+        self.controller = Some(controller);
+    }
+
+    /// Move this body along with the platform it stands on
+    #[inline]
+    pub fn carry(&mut self, displacement: Vector<Real>) {
+        self.carry = displacement;
+    }
+
+    /// Interpolate between the current and the next isometry.
+    /// Use the factor returned by `World::step` for rendering.
+    #[inline]
+    pub fn interpolated_isometry(&self, alpha: Real) -> Isometry<Real> {
+        self.common.isometry.lerp_slerp(&self.next_isometry, alpha)
+    }
+
     /// Apply the collision to this body
     #[inline]
-    pub fn add_contact(&mut self, hit: ShapeCastHit, other_weight: Option<Real>, payload: P) {
+    pub fn add_contact<O>(
+        &mut self,
+        hit: ShapeCastHit,
+        other: &O,
+        other_weight: Option<Real>,
+        payload: P,
+    ) where
+        O: Object,
+    {
         // Compare the weight of the two object to deduce
         // which one should push back the other more.
         let weight_ratio = if let Some(w) = other_weight {
@@ -157,13 +438,75 @@ impl<P> KinematicBody<P> {
             1.0
         };
 
-        // add the hit result to the set
-        self.contacts
-            .push(Box::new(Contact::new(hit, weight_ratio, payload)));
+        self.push_contact(
+            hit,
+            weight_ratio,
+            payload,
+            other.shared_shape().clone(),
+            *other.isometry(),
+            other.velocity(),
+        );
+    }
+
+    /// Apply the collision with a fixed shape which is not an object, such as a tile
+    #[cfg(feature = "2d")]
+    #[inline]
+    pub(crate) fn add_fixed_contact(
+        &mut self,
+        hit: ShapeCastHit,
+        shape: Arc<dyn Shape>,
+        isometry: Isometry<Real>,
+        payload: P,
+    ) {
+        self.push_contact(hit, 1.0, payload, shape, isometry, Vector::zeros());
+    }
+
+    /// Add the hit result to the set of contacts
+    fn push_contact(
+        &mut self,
+        hit: ShapeCastHit,
+        weight_ratio: Real,
+        payload: P,
+        shape: Arc<dyn Shape>,
+        isometry: Isometry<Real>,
+        velocity: Vector<Real>,
+    ) {
+        self.contacts.push(Box::new(Contact::new(
+            hit,
+            weight_ratio,
+            payload,
+            shape,
+            isometry,
+            velocity,
+        )));
+    }
+
+    /// Apply the hits to the body.
+    /// Additional iterations re-test the corrected motion against the contacts.
+    /// Bodies with a character controller are only carried here,
+    /// the world slides them along the surfaces afterward.
+    pub fn apply_contacts(&mut self, delta_time: Real, epsilon: Real, iterations: u32) {
+        // follow the platform this body stands on
+        if !is_null(&self.carry, epsilon) {
+            let translation = Translation::from(self.carry);
+            self.next_isometry.append_translation_mut(&translation);
+        }
+
+        // characters are slid along the surfaces by the world instead of being pushed back
+        if self.controller.is_some() {
+            return;
+        }
+        self.push_back(delta_time, epsilon, iterations);
+
+        // check if the body ended up standing on the floor
+        self.on_floor = self
+            .contacts
+            .iter()
+            .any(|contact| self.is_floor(&-world_normal(&self.common.isometry, contact.hit())));
     }
 
-    /// Apply the hits to the body
-    pub fn apply_contacts(&mut self, delta_time: Real, epsilon: Real) {
+    /// Push back the body according to the contacts
+    fn push_back(&mut self, delta_time: Real, epsilon: Real, iterations: u32) {
         // order the hits from closest to furthest
         self.contacts.sort_by(|a, b| a.order(b, epsilon));
 
@@ -172,7 +515,7 @@ impl<P> KinematicBody<P> {
         for contact in self.contacts.iter() {
             // push back the object according to its mass
             let hit = contact.hit();
-            let normal = hit.normal1.into_inner();
+            let normal = world_normal(&self.common.isometry, hit);
             let ratio = contact.weight_ratio();
 
             // push back the object
@@ -197,5 +540,97 @@ impl<P> KinematicBody<P> {
             let translation = Translation::from(offset * delta_time);
             self.next_isometry.append_translation_mut(&translation);
         }
+
+        // re-test the corrected motion to get out of corners and wedges
+        for _ in 1..iterations {
+            if self.resolve_contacts(delta_time, epsilon) {
+                break;
+            }
+        }
+    }
+
+    /// Push back the next isometry out of the contacts it still penetrates.
+    /// Return true if the motion is free of any contact.
+    fn resolve_contacts(&mut self, delta_time: Real, epsilon: Real) -> bool {
+        let options = ShapeCastOptions::with_max_time_of_impact(delta_time);
+        let mut resolved = true;
+        for contact in self.contacts.iter() {
+            // motion of the body once corrected by the previous contacts
+            let motion =
+                self.next_isometry.translation.vector - self.common.isometry.translation.vector;
+            let velocity = motion / delta_time;
+
+            let hit = cast_shapes(
+                &self.common.isometry,
+                &velocity,
+                self.common.shape.as_ref(),
+                contact.isometry(),
+                &contact.velocity(),
+                contact.shape(),
+                options,
+            );
+
+            if let Some(hit) = hit {
+                // only the motion going toward the other object matters
+                let normal = world_normal(&self.common.isometry, &hit);
+                let relative = motion - contact.velocity() * delta_time;
+                let dot = normal.dot(&relative);
+                if dot > epsilon {
+                    // remove the part of the motion past the time of impact
+                    let depth = dot * (1.0 - hit.time_of_impact / delta_time);
+                    let translation = Translation::from(-normal * (depth * contact.weight_ratio()));
+                    self.next_isometry.append_translation_mut(&translation);
+                    resolved = false;
+                }
+            }
+        }
+        resolved
+    }
+}
+
+/// State of a kinematic body restored on rollback
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BodyState {
+    isometry: Isometry<Real>,
+    next_isometry: Isometry<Real>,
+    velocity: Vector<Real>,
+    drop_time: Real,
+    on_floor: bool,
+    was_on_floor: bool,
+    sleeping: bool,
+    idle_ticks: u32,
+    controller: Option<CharacterController>,
+}
+
+impl<P> KinematicBody<P> {
+    /// Capture the state of the body
+    pub(crate) fn state(&self) -> BodyState {
+        BodyState {
+            isometry: self.common.isometry,
+            next_isometry: self.next_isometry,
+            velocity: self.velocity,
+            drop_time: self.drop_time,
+            on_floor: self.on_floor,
+            was_on_floor: self.was_on_floor,
+            sleeping: self.sleeping,
+            idle_ticks: self.idle_ticks,
+            controller: self.controller,
+        }
+    }
+
+    /// Go back to a captured state, the results of the last tick are discarded
+    pub(crate) fn set_state(&mut self, state: &BodyState) {
+        self.common.isometry = state.isometry;
+        self.next_isometry = state.next_isometry;
+        self.velocity = state.velocity;
+        self.drop_time = state.drop_time;
+        self.on_floor = state.on_floor;
+        self.was_on_floor = state.was_on_floor;
+        self.sleeping = state.sleeping;
+        self.idle_ticks = state.idle_ticks;
+        self.controller = state.controller;
+        self.desired_motion = Vector::zeros();
+        self.carry = Vector::zeros();
+        self.contacts.clear();
     }
 }
@@ -0,0 +1,181 @@
+//! Moving body which blocks and carries kinematic bodies
+
+#[cfg(feature = "serde")]
+use super::shape::SavedShape;
+use super::{world_normal, CommonData, Mask, Object, DEFAULT_FLOOR_ANGLE, MASK_ALL};
+use crate::world::aabb::Aabb;
+use alloc::sync::Arc;
+use delegate::delegate;
+use parry::{
+    math::{Isometry, Real, Vector},
+    na::{ComplexField, Unit},
+    query::ShapeCastHit,
+    shape::Shape,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A moving platform in the world
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MovingPlatform<P = ()> {
+    /// Shape, isometry and handle
+    common: CommonData<P>,
+
+    /// Specify the layer this body belongs to
+    layer: Mask,
+
+    /// Velocity of the platform.
+    /// It can be accessed directly to modify each coordinate individually.
+    pub velocity: Vector<Real>,
+
+    /// Target isometry at the next tick
+    next_isometry: Isometry<Real>,
+
+    /// Direction in which riders must stand on this platform
+    up: Unit<Vector<Real>>,
+
+    /// Cosine of the maximum angle between the up vector and the contact normal
+    min_floor_dot: Real,
+}
+
+impl<P> MovingPlatform<P> {
+    /// Build a new moving platform
+    #[inline]
+    pub fn new(shape: Arc<dyn Shape>, isometry: Isometry<Real>, payload: P, layer: Mask) -> Self {
+        Self {
+            common: CommonData::new(shape, isometry, payload),
+            layer,
+            velocity: Vector::zeros(),
+            next_isometry: isometry,
+            up: Vector::y_axis(),
+            min_floor_dot: ComplexField::cos(DEFAULT_FLOOR_ANGLE),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<P> SavedShape for MovingPlatform<P> {
+    #[inline]
+    fn restore_shape(&mut self, shape: Arc<dyn Shape>) {
+        self.common.restore_shape(shape);
+    }
+}
+
+impl<P> Object for MovingPlatform<P> {
+    type Payload = P;
+
+    delegate! {
+        to self.common {
+            #[inline] fn shape(&self) -> &dyn Shape;
+            #[inline] fn shared_shape(&self) -> &Arc<dyn Shape>;
+            #[inline] fn isometry(&self) -> &Isometry<Real>;
+            #[inline] fn payload(&self) -> &P;
+            #[inline] fn payload_mut(&mut self) -> &mut P;
+        }
+    }
+
+    /// Compute the AABB of this moving platform
+    #[inline]
+    fn aabb(&self) -> Aabb {
+        Aabb::new(
+            self.common
+                .shape
+                .compute_swept_aabb(&self.common.isometry, &self.next_isometry),
+            self.layer,
+            MASK_ALL,
+        )
+    }
+
+    #[inline]
+    fn layer(&self) -> Mask {
+        self.layer
+    }
+
+    /// Get the velocity of the platform
+    #[inline]
+    fn velocity(&self) -> Vector<Real> {
+        self.velocity
+    }
+}
+
+impl<P> MovingPlatform<P> {
+    /// Compute the next isometry by applying the velocity
+    pub fn pre_update(&mut self, delta_time: Real) {
+        // submit the computed new isometry
+        self.common.isometry = self.next_isometry;
+
+        // platforms are never pushed back so the next isometry is final
+        self.next_isometry.translation.vector += self.velocity * delta_time;
+    }
+
+    /// Access the next isometry of the platform
+    #[inline]
+    pub fn next_isometry(&self) -> &Isometry<Real> {
+        &self.next_isometry
+    }
+
+    /// Interpolate between the current and the next isometry.
+    /// Use the factor returned by `World::step` for rendering.
+    #[inline]
+    pub fn interpolated_isometry(&self, alpha: Real) -> Isometry<Real> {
+        self.common.isometry.lerp_slerp(&self.next_isometry, alpha)
+    }
+
+    /// Displacement of the platform over the current tick
+    #[inline]
+    pub fn displacement(&self) -> Vector<Real> {
+        self.next_isometry.translation.vector - self.common.isometry.translation.vector
+    }
+
+    /// Direction in which riders must stand on this platform
+    #[inline]
+    pub fn up(&self) -> &Unit<Vector<Real>> {
+        &self.up
+    }
+
+    /// Change the direction in which riders must stand on this platform
+    #[inline]
+    pub fn set_up(&mut self, up: Unit<Vector<Real>>) {
+        self.up = up;
+    }
+
+    /// Change the maximum angle between the up vector and a surface a body can stand on
+    #[inline]
+    pub fn set_floor_angle(&mut self, angle: Real) {
+        self.min_floor_dot = ComplexField::cos(angle);
+    }
+
+    /// Check if a body placed at the given isometry stands on this platform
+    /// given the hit from the body point of view
+    #[inline]
+    pub fn is_carrying(&self, isometry: &Isometry<Real>, hit: &ShapeCastHit) -> bool {
+        // the normal on the body points toward the platform, hence downward
+        -world_normal(isometry, hit).dot(&self.up) >= self.min_floor_dot
+    }
+}
+
+/// State of a moving platform restored on rollback
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PlatformState {
+    isometry: Isometry<Real>,
+    next_isometry: Isometry<Real>,
+    velocity: Vector<Real>,
+}
+
+impl<P> MovingPlatform<P> {
+    /// Capture the state of the platform
+    pub(crate) fn state(&self) -> PlatformState {
+        PlatformState {
+            isometry: self.common.isometry,
+            next_isometry: self.next_isometry,
+            velocity: self.velocity,
+        }
+    }
+
+    /// Go back to a captured state
+    pub(crate) fn set_state(&mut self, state: &PlatformState) {
+        self.common.isometry = state.isometry;
+        self.next_isometry = state.next_isometry;
+        self.velocity = state.velocity;
+    }
+}
@@ -0,0 +1,151 @@
+//! Serializable description of the shapes of the objects.
+//! Shapes are shared behind trait objects, they are saved as one of the supported parry shapes.
+//! A world saves each shape once and the objects refer to it by index.
+
+use alloc::{sync::Arc, vec::Vec};
+#[cfg(feature = "2d")]
+use parry::shape::ConvexPolygon;
+#[cfg(feature = "3d")]
+use parry::shape::{Cone, ConvexPolyhedron, Cylinder};
+use parry::{
+    math::{Isometry, Real},
+    shape::{
+        Ball, Capsule, Compound, Cuboid, HalfSpace, HeightField, Polyline, Segment, Shape,
+        SharedShape, TriMesh, Triangle, TypedShape,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+/// Shape supported by the serialization
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ShapeData {
+    /// Ball
+    Ball(Ball),
+
+    /// Box
+    Cuboid(Cuboid),
+
+    /// Capsule
+    Capsule(Capsule),
+
+    /// Segment
+    Segment(Segment),
+
+    /// Triangle
+    Triangle(Triangle),
+
+    /// Half space
+    HalfSpace(HalfSpace),
+
+    /// Convex polygon
+    #[cfg(feature = "2d")]
+    ConvexPolygon(ConvexPolygon),
+
+    /// Convex polyhedron
+    #[cfg(feature = "3d")]
+    ConvexPolyhedron(ConvexPolyhedron),
+
+    /// Cylinder
+    #[cfg(feature = "3d")]
+    Cylinder(Cylinder),
+
+    /// Cone
+    #[cfg(feature = "3d")]
+    Cone(Cone),
+
+    /// Polyline
+    Polyline(Polyline),
+
+    /// Triangle mesh
+    TriMesh(TriMesh),
+
+    /// Height field
+    HeightField(HeightField),
+
+    /// Compound of shapes along with their isometries
+    Compound(Vec<(Isometry<Real>, ShapeData)>),
+}
+
+impl ShapeData {
+    /// Describe a shape, return `None` if the shape is not supported
+    pub fn from_shape(shape: &dyn Shape) -> Option<Self> {
+        Some(match shape.as_typed_shape() {
+            TypedShape::Ball(ball) => Self::Ball(*ball),
+            TypedShape::Cuboid(cuboid) => Self::Cuboid(*cuboid),
+            TypedShape::Capsule(capsule) => Self::Capsule(*capsule),
+            TypedShape::Segment(segment) => Self::Segment(*segment),
+            TypedShape::Triangle(triangle) => Self::Triangle(*triangle),
+            TypedShape::HalfSpace(half_space) => Self::HalfSpace(*half_space),
+            #[cfg(feature = "2d")]
+            TypedShape::ConvexPolygon(polygon) => Self::ConvexPolygon(polygon.clone()),
+            #[cfg(feature = "3d")]
+            TypedShape::ConvexPolyhedron(polyhedron) => Self::ConvexPolyhedron(polyhedron.clone()),
+            #[cfg(feature = "3d")]
+            TypedShape::Cylinder(cylinder) => Self::Cylinder(*cylinder),
+            #[cfg(feature = "3d")]
+            TypedShape::Cone(cone) => Self::Cone(*cone),
+            TypedShape::Polyline(polyline) => Self::Polyline(polyline.clone()),
+            TypedShape::TriMesh(mesh) => Self::TriMesh(mesh.clone()),
+            TypedShape::HeightField(field) => Self::HeightField(field.clone()),
+            TypedShape::Compound(compound) => Self::Compound(
+                compound
+                    .shapes()
+                    .iter()
+                    .map(|(isometry, part)| Some((*isometry, Self::from_shape(part.as_ref())?)))
+                    .collect::<Option<_>>()?,
+            ),
+            _ => return None,
+        })
+    }
+
+    /// Build the shape described.
+    /// Return `None` if a compound is empty or holds other composite shapes.
+    pub fn into_shape(self) -> Option<Arc<dyn Shape>> {
+        Some(match self {
+            Self::Ball(ball) => Arc::new(ball),
+            Self::Cuboid(cuboid) => Arc::new(cuboid),
+            Self::Capsule(capsule) => Arc::new(capsule),
+            Self::Segment(segment) => Arc::new(segment),
+            Self::Triangle(triangle) => Arc::new(triangle),
+            Self::HalfSpace(half_space) => Arc::new(half_space),
+            #[cfg(feature = "2d")]
+            Self::ConvexPolygon(polygon) => Arc::new(polygon),
+            #[cfg(feature = "3d")]
+            Self::ConvexPolyhedron(polyhedron) => Arc::new(polyhedron),
+            #[cfg(feature = "3d")]
+            Self::Cylinder(cylinder) => Arc::new(cylinder),
+            #[cfg(feature = "3d")]
+            Self::Cone(cone) => Arc::new(cone),
+            Self::Polyline(polyline) => Arc::new(polyline),
+            Self::TriMesh(mesh) => Arc::new(mesh),
+            Self::HeightField(field) => Arc::new(field),
+            Self::Compound(parts) => {
+                let parts: Vec<_> = parts
+                    .into_iter()
+                    .map(|(isometry, part)| Some((isometry, SharedShape(part.into_shape()?))))
+                    .collect::<Option<_>>()?;
+
+                // parry panics on the compounds it cannot build
+                if parts.is_empty()
+                    || parts
+                        .iter()
+                        .any(|(_, part)| part.as_composite_shape().is_some())
+                {
+                    return None;
+                }
+                Arc::new(Compound::new(parts))
+            }
+        })
+    }
+}
+
+/// Object whose shape is saved apart from it, in a table shared by the objects of a world
+pub(crate) trait SavedShape {
+    /// Give back its shape to a loaded object
+    fn restore_shape(&mut self, shape: Arc<dyn Shape>);
+}
+
+/// Shape given to the loaded objects until their own shape is restored
+pub(crate) fn placeholder() -> Arc<dyn Shape> {
+    Arc::new(Ball::new(0.0))
+}
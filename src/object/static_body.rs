@@ -1,22 +1,31 @@
 //! Fixed body which does not report collisions
 
-use super::{CommonData, Mask, Object, MASK_ALL};
-use crate::world::aabb::Aabb;
+#[cfg(feature = "serde")]
+use super::shape::SavedShape;
+use super::{world_normal, CommonData, Mask, Object, MASK_ALL};
+use crate::{object::kinematic_body::KinematicBody, world::aabb::Aabb};
 use alloc::sync::Arc;
-use bvh_arena::VolumeHandle;
 use delegate::delegate;
 use parry::{
-    math::{Isometry, Real},
+    math::{Isometry, Real, Vector},
+    na::Unit,
+    query::{self, ShapeCastHit, ShapeCastStatus},
     shape::Shape,
 };
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 /// A fixed body in the world
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct StaticBody<P = ()> {
     /// Shape, isometry and handle
     common: CommonData<P>,
 
     /// Specify the layer this body belongs to
     layer: Mask,
+
+    /// Direction from which this body blocks kinematic bodies, if it only blocks from one side
+    one_way: Option<Unit<Vector<Real>>>,
 }
 
 impl<P> StaticBody<P> {
@@ -26,19 +35,26 @@ impl<P> StaticBody<P> {
         Self {
             common: CommonData::new(shape, isometry, payload),
             layer,
+            one_way: None,
         }
     }
 }
 
+#[cfg(feature = "serde")]
+impl<P> SavedShape for StaticBody<P> {
+    #[inline]
+    fn restore_shape(&mut self, shape: Arc<dyn Shape>) {
+        self.common.restore_shape(shape);
+    }
+}
+
 impl<P> Object for StaticBody<P> {
     type Payload = P;
 
     delegate! {
         to self.common {
-            #[inline] fn set_handle(&mut self, handle: VolumeHandle);
-            #[inline] fn unset_handle(&mut self);
-            #[inline] fn handle(&self) -> Option<VolumeHandle>;
             #[inline] fn shape(&self) -> &dyn Shape;
+            #[inline] fn shared_shape(&self) -> &Arc<dyn Shape>;
             #[inline] fn isometry(&self) -> &Isometry<Real>;
             #[inline] fn payload(&self) -> &P;
             #[inline] fn payload_mut(&mut self) -> &mut P;
@@ -66,3 +82,50 @@ impl<P> Object for StaticBody<P> {
         Some(self)
     }
 }
+
+impl<P> StaticBody<P> {
+    /// Direction from which this body blocks kinematic bodies, if it only blocks from one side
+    #[inline]
+    pub fn one_way(&self) -> Option<&Unit<Vector<Real>>> {
+        self.one_way.as_ref()
+    }
+
+    /// Make this body only block kinematic bodies coming from the given direction.
+    /// Bodies coming from any other direction pass through it.
+    #[inline]
+    pub fn set_one_way(&mut self, direction: Option<Unit<Vector<Real>>>) {
+        self.one_way = direction;
+    }
+
+    /// Check if this body blocks the kinematic body placed at the given isometry
+    /// given the hit from the kinematic body point of view
+    pub fn blocks<B>(
+        &self,
+        body: &KinematicBody<B>,
+        isometry: &Isometry<Real>,
+        hit: &ShapeCastHit,
+        epsilon: Real,
+    ) -> bool {
+        let Some(direction) = self.one_way.as_ref() else {
+            return true;
+        };
+
+        // the body asked to drop through
+        if body.is_dropping() {
+            return false;
+        }
+
+        // the body is passing through if it is already inside this body
+        if hit.status == ShapeCastStatus::PenetratingOrWithinTargetDist {
+            let depth = query::contact(isometry, body.shape(), self.isometry(), self.shape(), 0.0)
+                .unwrap_or(None)
+                .map_or(0.0, |contact| -contact.dist);
+            if depth > epsilon {
+                return false;
+            }
+        }
+
+        // the normal on the body points toward this body, it must come from the blocking side
+        -world_normal(isometry, hit).dot(direction) > 0.0
+    }
+}
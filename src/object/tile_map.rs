@@ -0,0 +1,560 @@
+//! Grid of tiles blocking the kinematic bodies.
+//! Cells are looked up directly instead of going through the broadphase.
+
+use super::{Mask, MASK_ALL};
+use crate::world::aabb::Aabb;
+use alloc::{sync::Arc, vec, vec::Vec};
+use parry::{
+    bounding_volume as p,
+    math::{Isometry, Point, Real, Vector},
+    na::ComplexField,
+    query::{Ray, RayIntersection},
+    shape::{ConvexPolygon, Cuboid, Shape},
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Identifier of a tile, mapped to its collision type by the palette of the map
+pub type TileId = u32;
+
+/// Coordinates of a cell, the cell (0, 0) is at the bottom left corner of the map
+pub type Cell = (usize, usize);
+
+/// Shape of a tile along with its offset from the bottom left corner of the cell
+type TileShape = (Arc<dyn Shape>, Vector<Real>);
+
+/// Collision type of a tile
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Tile {
+    /// The tile does not block anything
+    #[default]
+    Empty,
+
+    /// The tile fills its whole cell
+    Solid,
+
+    /// 45° slope filling the half of the cell on the side of the corner
+    Slope(Corner),
+
+    /// 22.5° slope spanning two cells, the surface drops by half a cell per cell.
+    /// The highest part of the slope is on the side of the corner.
+    GentleSlope {
+        /// Corner of the cell where the slope is the highest
+        corner: Corner,
+
+        /// Specify if this is the high part of the slope, or the low one
+        high: bool,
+    },
+
+    /// Block filling the half of the cell on the given side
+    Half(Side),
+}
+
+/// Corner of a cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Corner {
+    /// Corner at the lowest coordinates
+    BottomLeft,
+
+    /// Corner at the highest horizontal and lowest vertical coordinates
+    BottomRight,
+
+    /// Corner at the lowest horizontal and highest vertical coordinates
+    TopLeft,
+
+    /// Corner at the highest coordinates
+    TopRight,
+}
+
+/// Side of a cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Side {
+    /// Side facing down
+    Bottom,
+
+    /// Side facing up
+    Top,
+
+    /// Side facing left
+    Left,
+
+    /// Side facing right
+    Right,
+}
+
+impl Corner {
+    /// Horizontal and vertical sides meeting at this corner
+    #[inline]
+    pub fn sides(self) -> (Side, Side) {
+        match self {
+            Self::BottomLeft => (Side::Bottom, Side::Left),
+            Self::BottomRight => (Side::Bottom, Side::Right),
+            Self::TopLeft => (Side::Top, Side::Left),
+            Self::TopRight => (Side::Top, Side::Right),
+        }
+    }
+
+    /// Move a point of a cell of the given size from the bottom left corner to this one
+    fn mirror(self, point: Point<Real>, size: Real) -> Point<Real> {
+        match self {
+            Self::BottomLeft => point,
+            Self::BottomRight => Point::new(size - point.x, point.y),
+            Self::TopLeft => Point::new(point.x, size - point.y),
+            Self::TopRight => Point::new(size - point.x, size - point.y),
+        }
+    }
+}
+
+impl Tile {
+    /// Check if the tile covers the whole edge of its cell on the given side
+    pub fn covers(&self, side: Side) -> bool {
+        match *self {
+            Self::Empty => false,
+            Self::Solid => true,
+            Self::Slope(corner) => {
+                let (horizontal, vertical) = corner.sides();
+                side == horizontal || side == vertical
+            }
+            Self::GentleSlope { corner, high } => {
+                let (horizontal, vertical) = corner.sides();
+                side == horizontal || (high && side == vertical)
+            }
+            Self::Half(half) => side == half,
+        }
+    }
+
+    /// Build the shape of the tile for cells of the given size.
+    /// Also return the offset of the shape from the bottom left corner of the cell.
+    fn build_shape(&self, size: Real) -> Option<TileShape> {
+        let half = size * 0.5;
+        let quarter = size * 0.25;
+        match *self {
+            Self::Empty => None,
+            Self::Solid => Some((
+                Arc::new(Cuboid::new(Vector::new(half, half))),
+                Vector::new(half, half),
+            )),
+            Self::Half(side) => {
+                let offset = match side {
+                    Side::Bottom => Vector::new(half, quarter),
+                    Side::Top => Vector::new(half, size - quarter),
+                    Side::Left => Vector::new(quarter, half),
+                    Side::Right => Vector::new(size - quarter, half),
+                };
+                let half_extents = match side {
+                    Side::Bottom | Side::Top => Vector::new(half, quarter),
+                    Side::Left | Side::Right => Vector::new(quarter, half),
+                };
+                Some((Arc::new(Cuboid::new(half_extents)), offset))
+            }
+            Self::Slope(_) | Self::GentleSlope { .. } => {
+                let polygon = ConvexPolygon::from_convex_hull(&self.outline(size))?;
+                Some((Arc::new(polygon), Vector::zeros()))
+            }
+        }
+    }
+
+    /// Corners of the tile in a cell of the given size, relative to the bottom left corner of the cell
+    pub(crate) fn outline(&self, size: Real) -> Vec<Point<Real>> {
+        let half = size * 0.5;
+        let (corner, points) = match *self {
+            Self::Empty => return Vec::new(),
+            Self::Solid => (
+                Corner::BottomLeft,
+                vec![
+                    Point::new(0.0, 0.0),
+                    Point::new(size, 0.0),
+                    Point::new(size, size),
+                    Point::new(0.0, size),
+                ],
+            ),
+            Self::Half(side) => {
+                let (mins, maxs) = match side {
+                    Side::Bottom => (Point::new(0.0, 0.0), Point::new(size, half)),
+                    Side::Top => (Point::new(0.0, half), Point::new(size, size)),
+                    Side::Left => (Point::new(0.0, 0.0), Point::new(half, size)),
+                    Side::Right => (Point::new(half, 0.0), Point::new(size, size)),
+                };
+                (
+                    Corner::BottomLeft,
+                    vec![
+                        mins,
+                        Point::new(maxs.x, mins.y),
+                        maxs,
+                        Point::new(mins.x, maxs.y),
+                    ],
+                )
+            }
+            Self::Slope(corner) => (
+                corner,
+                vec![
+                    Point::new(0.0, 0.0),
+                    Point::new(size, 0.0),
+                    Point::new(0.0, size),
+                ],
+            ),
+            Self::GentleSlope { corner, high: true } => (
+                corner,
+                vec![
+                    Point::new(0.0, 0.0),
+                    Point::new(size, 0.0),
+                    Point::new(size, half),
+                    Point::new(0.0, size),
+                ],
+            ),
+            Self::GentleSlope {
+                corner,
+                high: false,
+            } => (
+                corner,
+                vec![
+                    Point::new(0.0, 0.0),
+                    Point::new(size, 0.0),
+                    Point::new(0.0, half),
+                ],
+            ),
+        };
+        points
+            .into_iter()
+            .map(|point| corner.mirror(point, size))
+            .collect()
+    }
+}
+
+/// Grid of tiles in the world
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TileMap<P = ()> {
+    /// Position of the bottom left corner of the map
+    origin: Vector<Real>,
+
+    /// Size of the cells
+    tile_size: Real,
+
+    /// Number of columns
+    width: usize,
+
+    /// Number of rows
+    height: usize,
+
+    /// Tile ids of the cells, row by row from the bottom
+    tiles: Vec<TileId>,
+
+    /// Collision type of each tile id
+    palette: Vec<Tile>,
+
+    /// Shape of each tile id along with its offset in the cell
+    #[cfg_attr(feature = "serde", serde(skip_serializing))]
+    shapes: Vec<Option<TileShape>>,
+
+    /// Specify the layer this map belongs to
+    layer: Mask,
+
+    /// Arbitrary payload
+    payload: P,
+}
+
+impl<P> TileMap<P> {
+    /// Build a new tile map, all the cells start with the tile id 0.
+    /// The palette maps the tile ids to their collision type,
+    /// the ids missing from the palette are empty.
+    pub fn new(
+        origin: Vector<Real>,
+        tile_size: Real,
+        width: usize,
+        height: usize,
+        palette: Vec<Tile>,
+        payload: P,
+        layer: Mask,
+    ) -> Self {
+        let shapes = palette
+            .iter()
+            .map(|tile| tile.build_shape(tile_size))
+            .collect();
+        Self {
+            origin,
+            tile_size,
+            width,
+            height,
+            tiles: vec![0; width * height],
+            palette,
+            shapes,
+            layer,
+            payload,
+        }
+    }
+
+    /// Position of the bottom left corner of the map
+    #[inline]
+    pub fn origin(&self) -> &Vector<Real> {
+        &self.origin
+    }
+
+    /// Size of the cells
+    #[inline]
+    pub fn tile_size(&self) -> Real {
+        self.tile_size
+    }
+
+    /// Number of columns
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of rows
+    #[inline]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Get the layer(s) this map belongs to
+    #[inline]
+    pub fn layer(&self) -> Mask {
+        self.layer
+    }
+
+    /// Access the payload defined on this map
+    #[inline]
+    pub fn payload(&self) -> &P {
+        &self.payload
+    }
+
+    /// Access the payload defined on this map
+    #[inline]
+    pub fn payload_mut(&mut self) -> &mut P {
+        &mut self.payload
+    }
+
+    /// Collision type of each tile id
+    #[inline]
+    pub fn palette(&self) -> &[Tile] {
+        &self.palette
+    }
+
+    /// Change the collision type of a tile id
+    pub fn set_palette(&mut self, id: TileId, tile: Tile) {
+        let index = id as usize;
+        if index >= self.palette.len() {
+            self.palette.resize(index + 1, Tile::Empty);
+            self.shapes.resize(index + 1, None);
+        }
+        self.palette[index] = tile;
+        self.shapes[index] = tile.build_shape(self.tile_size);
+    }
+
+    /// Tile id of a cell, if the cell is in the map
+    #[inline]
+    pub fn tile_id(&self, cell: Cell) -> Option<TileId> {
+        self.index(cell).map(|index| self.tiles[index])
+    }
+
+    /// Change the tile id of a cell and return the previous one.
+    /// Nothing happens if the cell is out of the map.
+    pub fn set_tile_id(&mut self, cell: Cell, id: TileId) -> Option<TileId> {
+        let index = self.index(cell)?;
+        Some(core::mem::replace(&mut self.tiles[index], id))
+    }
+
+    /// Collision type of a cell, the cells out of the map are empty
+    #[inline]
+    pub fn tile(&self, cell: Cell) -> Tile {
+        self.tile_id(cell)
+            .and_then(|id| self.palette.get(id as usize).copied())
+            .unwrap_or_default()
+    }
+
+    /// Find the cell containing the given point
+    pub fn cell_at(&self, point: &Point<Real>) -> Option<Cell> {
+        let local = (point.coords - self.origin) / self.tile_size;
+        let (x, y) = (ComplexField::floor(local.x), ComplexField::floor(local.y));
+        if x < 0.0 || y < 0.0 || x >= self.width as Real || y >= self.height as Real {
+            return None;
+        }
+        Some((x as usize, y as usize))
+    }
+
+    /// Bounds of a cell
+    pub fn cell_aabb(&self, cell: Cell) -> p::Aabb {
+        let mins = self.origin + Vector::new(cell.0 as Real, cell.1 as Real) * self.tile_size;
+        let maxs = mins + Vector::repeat(self.tile_size);
+        p::Aabb::new(Point::from(mins), Point::from(maxs))
+    }
+
+    /// Bounds of the whole map
+    pub fn bounds(&self) -> p::Aabb {
+        let size = Vector::new(self.width as Real, self.height as Real) * self.tile_size;
+        p::Aabb::new(Point::from(self.origin), Point::from(self.origin + size))
+    }
+
+    /// Create an Axis-Aligned Bounding Box for the whole map
+    #[inline]
+    pub fn aabb(&self) -> Aabb {
+        Aabb::new(self.bounds(), self.layer, MASK_ALL)
+    }
+
+    /// Index of a cell in the grid
+    #[inline]
+    fn index(&self, cell: Cell) -> Option<usize> {
+        (cell.0 < self.width && cell.1 < self.height).then(|| cell.1 * self.width + cell.0)
+    }
+
+    /// Shape of a cell along with its isometry, if the cell is not empty
+    pub fn collider(&self, cell: Cell) -> Option<(&Arc<dyn Shape>, Isometry<Real>)> {
+        let id = self.tile_id(cell)?;
+        let (shape, offset) = self.shapes.get(id as usize)?.as_ref()?;
+        let corner = self.origin + Vector::new(cell.0 as Real, cell.1 as Real) * self.tile_size;
+        Some((shape, Isometry::new(corner + offset, 0.0)))
+    }
+
+    /// Report the shape of each non-empty cell overlapping the given bounds
+    pub fn for_each_tile(
+        &self,
+        aabb: &p::Aabb,
+        mut on_tile: impl FnMut(Cell, &Arc<dyn Shape>, &Isometry<Real>),
+    ) {
+        let mins = (aabb.mins.coords - self.origin) / self.tile_size;
+        let maxs = (aabb.maxs.coords - self.origin) / self.tile_size;
+        if maxs.x < 0.0 || maxs.y < 0.0 {
+            return;
+        }
+
+        // clamp the range of cells to the map
+        let x0 = ComplexField::floor(mins.x).max(0.0) as usize;
+        let y0 = ComplexField::floor(mins.y).max(0.0) as usize;
+        let x1 = (ComplexField::floor(maxs.x) as usize).min(self.width.saturating_sub(1));
+        let y1 = (ComplexField::floor(maxs.y) as usize).min(self.height.saturating_sub(1));
+        if x0 >= self.width || y0 >= self.height {
+            return;
+        }
+
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                if let Some((shape, isometry)) = self.collider((x, y)) {
+                    on_tile((x, y), shape, &isometry);
+                }
+            }
+        }
+    }
+
+    /// Walk the cells crossed by the ray in order and test the non-empty ones.
+    /// The traversal stops once the callback returns false.
+    pub fn cast_ray(
+        &self,
+        ray: &Ray,
+        max_time_of_impact: Real,
+        solid: bool,
+        mut on_hit: impl FnMut(Cell, RayIntersection) -> bool,
+    ) {
+        // only walk the part of the ray inside the map
+        let Some((enter, exit)) = self.bounds().clip_ray_parameters(ray) else {
+            return;
+        };
+        let exit = exit.min(max_time_of_impact);
+        if enter > exit {
+            return;
+        }
+
+        // start from the cell where the ray enters the map
+        let entry = (ray.point_at(enter).coords - self.origin) / self.tile_size;
+        let mut cell = [0isize; 2];
+        let mut step = [0isize; 2];
+        let mut next = [Real::INFINITY; 2];
+        let mut delta = [Real::INFINITY; 2];
+        let limits = [self.width as isize, self.height as isize];
+        for axis in 0..2 {
+            cell[axis] = (ComplexField::floor(entry[axis]) as isize).clamp(0, limits[axis] - 1);
+
+            // time at which the ray crosses the next boundary on this axis
+            let direction = ray.dir[axis];
+            if direction != 0.0 {
+                step[axis] = if direction > 0.0 { 1 } else { -1 };
+                let boundary = cell[axis] + isize::from(direction > 0.0);
+                let boundary = self.origin[axis] + boundary as Real * self.tile_size;
+                next[axis] = (boundary - ray.origin[axis]) / direction;
+                delta[axis] = self.tile_size / direction.abs();
+            }
+        }
+
+        loop {
+            let current = (cell[0] as usize, cell[1] as usize);
+            if let Some((shape, isometry)) = self.collider(current)
+                && let Some(hit) =
+                    shape.cast_ray_and_get_normal(&isometry, ray, max_time_of_impact, solid)
+                && !on_hit(current, hit)
+            {
+                return;
+            }
+
+            // move to the next cell along the ray
+            let axis = if next[0] < next[1] { 0 } else { 1 };
+            if next[axis] > exit {
+                return;
+            }
+            cell[axis] += step[axis];
+            if cell[axis] < 0 || cell[axis] >= limits[axis] {
+                return;
+            }
+            next[axis] += delta[axis];
+        }
+    }
+
+    /// Check if the face of a tile with the given outward normal is buried against its neighbor.
+    /// Hits on such faces are ignored so that bodies slide over the seams between tiles.
+    pub fn is_internal_edge(&self, cell: Cell, normal: &Vector<Real>, epsilon: Real) -> bool {
+        let (neighbor, side) = if normal.x >= 1.0 - epsilon {
+            (cell.0.checked_add(1).map(|x| (x, cell.1)), Side::Left)
+        } else if normal.x <= epsilon - 1.0 {
+            (cell.0.checked_sub(1).map(|x| (x, cell.1)), Side::Right)
+        } else if normal.y >= 1.0 - epsilon {
+            (cell.1.checked_add(1).map(|y| (cell.0, y)), Side::Bottom)
+        } else if normal.y <= epsilon - 1.0 {
+            (cell.1.checked_sub(1).map(|y| (cell.0, y)), Side::Top)
+        } else {
+            return false;
+        };
+        neighbor.is_some_and(|neighbor| self.tile(neighbor).covers(side))
+    }
+}
+
+/// Tile map as it is saved, the shapes are rebuilt from the palette
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(rename = "TileMap")]
+struct TileMapData<P> {
+    origin: Vector<Real>,
+    tile_size: Real,
+    width: usize,
+    height: usize,
+    tiles: Vec<TileId>,
+    palette: Vec<Tile>,
+    layer: Mask,
+    payload: P,
+}
+
+#[cfg(feature = "serde")]
+impl<'de, P: Deserialize<'de>> Deserialize<'de> for TileMap<P> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = TileMapData::deserialize(deserializer)?;
+        if data.tiles.len() != data.width * data.height {
+            return Err(serde::de::Error::invalid_length(
+                data.tiles.len(),
+                &"one tile id per cell",
+            ));
+        }
+
+        let mut map = Self::new(
+            data.origin,
+            data.tile_size,
+            data.width,
+            data.height,
+            data.palette,
+            data.payload,
+            data.layer,
+        );
+        map.tiles = data.tiles;
+        Ok(map)
+    }
+}
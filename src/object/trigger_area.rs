@@ -1,9 +1,16 @@
 //! Trigger zone which detect intersection with kinematic bodies
 
 use super::{CommonData, Mask, Object, MASK_ALL};
-use crate::{object::kinematic_body::KinematicBody, world::aabb::Aabb};
-use alloc::sync::Arc;
-use bvh_arena::VolumeHandle;
+#[cfg(feature = "serde")]
+use crate::world::serial::Callbacks;
+use crate::{
+    object::kinematic_body::KinematicBody,
+    world::{aabb::Aabb, handle::KinematicHandle},
+    Shared,
+};
+#[cfg(feature = "serde")]
+use alloc::string::String;
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
 use delegate::delegate;
 use parry::{
     math::{Isometry, Real},
@@ -11,7 +18,29 @@ use parry::{
 };
 
 /// Function called on overlaps between this trigger and a body
-pub type OnOverlap<T, B> = fn(&mut TriggerArea<T, B>, &mut KinematicBody<B>);
+pub type OnOverlap<T, B> = fn(&mut TriggerArea<T, B>, &mut KinematicBody<B>, Overlap);
+
+/// Stage of the overlap between a trigger area and a kinematic body
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overlap {
+    /// The body started overlapping with the trigger area on this tick
+    Enter,
+
+    /// The body was already overlapping with the trigger area on the previous tick
+    Stay,
+
+    /// The body stopped overlapping with the trigger area on this tick
+    Exit,
+}
+
+/// Kinematic body currently inside a trigger area
+struct Occupant<B> {
+    /// Reference to the body
+    body: Shared<KinematicBody<B>>,
+
+    /// Specify if the body was found inside the area on the current tick
+    seen: bool,
+}
 
 /// A trigger zone in the world
 pub struct TriggerArea<P = (), B = ()> {
@@ -23,6 +52,16 @@ pub struct TriggerArea<P = (), B = ()> {
 
     /// Function called when this trigger area overlap with a kinematic body
     on_overlap: OnOverlap<P, B>,
+
+    /// Name the function is registered under, to save the trigger area
+    #[cfg(feature = "serde")]
+    callback_name: Option<String>,
+
+    /// Kinematic bodies currently inside this trigger area, by handle
+    occupants: BTreeMap<KinematicHandle<B>, Occupant<B>>,
+
+    /// Specify if the sleeping bodies inside must be woken up at the end of the update
+    wake_requested: bool,
 }
 
 impl<P, B> TriggerArea<P, B> {
@@ -39,8 +78,30 @@ impl<P, B> TriggerArea<P, B> {
             common: CommonData::new(shape, isometry, payload),
             mask,
             on_overlap,
+            #[cfg(feature = "serde")]
+            callback_name: None,
+            occupants: BTreeMap::new(),
+            wake_requested: false,
         }
     }
+
+    /// Create a new trigger area calling the function registered under the given name.
+    /// Return `None` if no function is registered under this name.
+    #[cfg(feature = "serde")]
+    pub fn with_callback(
+        shape: Arc<dyn Shape>,
+        isometry: Isometry<Real>,
+        payload: P,
+        mask: Mask,
+        callbacks: &Callbacks<P, B>,
+        name: impl Into<String>,
+    ) -> Option<Self> {
+        let name = name.into();
+        let on_overlap = callbacks.get(&name)?;
+        let mut area = Self::new(shape, isometry, payload, mask, on_overlap);
+        area.callback_name = Some(name);
+        Some(area)
+    }
 }
 
 impl<P, B> Object for TriggerArea<P, B> {
@@ -48,10 +109,8 @@ impl<P, B> Object for TriggerArea<P, B> {
 
     delegate! {
         to self.common {
-            #[inline] fn set_handle(&mut self, handle: VolumeHandle);
-            #[inline] fn unset_handle(&mut self);
-            #[inline] fn handle(&self) -> Option<VolumeHandle>;
             #[inline] fn shape(&self) -> &dyn Shape;
+            #[inline] fn shared_shape(&self) -> &Arc<dyn Shape>;
             #[inline] fn isometry(&self) -> &Isometry<Real>;
             #[inline] fn payload(&self) -> &P;
             #[inline] fn payload_mut(&mut self) -> &mut P;
@@ -77,7 +136,138 @@ impl<P, B> Object for TriggerArea<P, B> {
 impl<P, B> TriggerArea<P, B> {
     /// Access the callback defined for when this area overlap with a body
     #[inline]
-    pub fn on_overlap(&mut self, body: &mut KinematicBody<B>) {
-        (self.on_overlap)(self, body)
+    pub fn on_overlap(&mut self, body: &mut KinematicBody<B>, overlap: Overlap) {
+        (self.on_overlap)(self, body, overlap)
+    }
+
+    /// Iterate over the kinematic bodies currently inside this trigger area
+    pub fn occupants(&self) -> impl Iterator<Item = &Shared<KinematicBody<B>>> {
+        self.occupants.values().map(|occupant| &occupant.body)
+    }
+
+    /// Iterate over the handles of the kinematic bodies currently inside this trigger area
+    pub fn occupant_handles(&self) -> impl Iterator<Item = KinematicHandle<B>> + '_ {
+        self.occupants.keys().copied()
+    }
+
+    /// Number of kinematic bodies currently inside this trigger area
+    #[inline]
+    pub fn occupant_count(&self) -> usize {
+        self.occupants.len()
+    }
+
+    /// Check if the kinematic body is currently inside this trigger area
+    #[inline]
+    pub fn contains(&self, handle: KinematicHandle<B>) -> bool {
+        self.occupants.contains_key(&handle)
+    }
+
+    /// Ask the world to wake up the sleeping bodies inside this area at the end of the update.
+    /// It can be called from the overlap callback.
+    #[inline]
+    pub fn wake_occupants(&mut self) {
+        self.wake_requested = true;
+    }
+
+    /// Check if the sleeping bodies inside this area are about to be woken up
+    #[inline]
+    pub fn is_waking_occupants(&self) -> bool {
+        self.wake_requested
+    }
+
+    /// Restore a pending request to wake up the occupants
+    #[inline]
+    pub(crate) fn set_wake_requested(&mut self, wake_requested: bool) {
+        self.wake_requested = wake_requested;
+    }
+
+    /// Call the function registered under the given name from now on.
+    /// Return false and keep the current function if no function is registered under this name.
+    #[cfg(feature = "serde")]
+    pub fn set_callback(&mut self, callbacks: &Callbacks<P, B>, name: impl Into<String>) -> bool {
+        let name = name.into();
+        let Some(callback) = callbacks.get(&name) else {
+            return false;
+        };
+        self.on_overlap = callback;
+        self.callback_name = Some(name);
+        true
+    }
+
+    /// Name of the function called by this trigger area, if it was set from a registry
+    #[cfg(feature = "serde")]
+    #[inline]
+    pub fn callback_name(&self) -> Option<&str> {
+        self.callback_name.as_deref()
+    }
+
+    /// Replace the occupants of this area without notifying them
+    pub(crate) fn set_occupants(
+        &mut self,
+        bodies: impl IntoIterator<Item = (KinematicHandle<B>, Shared<KinematicBody<B>>)>,
+    ) {
+        self.occupants.clear();
+        self.occupants.extend(
+            bodies
+                .into_iter()
+                .map(|(handle, body)| (handle, Occupant { body, seen: false })),
+        );
+    }
+
+    /// Keep a sleeping body among the occupants without notifying it
+    pub(crate) fn keep(&mut self, handle: KinematicHandle<B>) {
+        if let Some(occupant) = self.occupants.get_mut(&handle) {
+            occupant.seen = true;
+        }
+    }
+
+    /// Register an overlap with a kinematic body on the current tick
+    pub(crate) fn overlap(
+        &mut self,
+        handle: KinematicHandle<B>,
+        shared: &Shared<KinematicBody<B>>,
+        body: &mut KinematicBody<B>,
+    ) -> Overlap {
+        let overlap = if let Some(occupant) = self.occupants.get_mut(&handle) {
+            occupant.seen = true;
+            Overlap::Stay
+        } else {
+            self.occupants.insert(
+                handle,
+                Occupant {
+                    body: shared.clone(),
+                    seen: true,
+                },
+            );
+            Overlap::Enter
+        };
+        self.on_overlap(body, overlap);
+        overlap
+    }
+
+    /// Notify the bodies which were not seen on the current tick that they left the area.
+    /// Return the bodies which left.
+    pub(crate) fn flush_exits(&mut self) -> Vec<Shared<KinematicBody<B>>> {
+        // split the bodies still inside from the ones which left
+        let mut left = Vec::new();
+        self.occupants.retain(|_, occupant| {
+            if core::mem::take(&mut occupant.seen) {
+                true
+            } else {
+                left.push(occupant.body.clone());
+                false
+            }
+        });
+
+        for body in left.iter() {
+            self.on_overlap(&mut body.write(), Overlap::Exit);
+        }
+        left
+    }
+
+    /// Check if waking up the bodies inside this area was requested, and clear the request
+    #[inline]
+    pub(crate) fn take_wake_request(&mut self) -> bool {
+        core::mem::take(&mut self.wake_requested)
     }
 }
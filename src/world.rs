@@ -3,43 +3,130 @@
 /// Base operations available on a world
 mod base;
 
+/// Climb small obstacles
+mod stairs;
+
+/// Keep bodies on the ground
+mod snap;
+
+/// Slide bodies along surfaces
+mod slide;
+
 /// Collection of objects
 pub mod set;
 
+/// Broadphase structures
+pub mod broadphase;
+
+/// Handles to the objects
+pub mod handle;
+
 /// Axis-Aligned Bounding Box (AABB)
 pub mod aabb;
 
+/// Fixed timestep driver
+pub mod step;
+
+/// Events reported during an update
+pub mod event;
+
+/// Spatial queries
+pub mod query;
+
+/// Filter for spatial queries
+pub mod filter;
+
+/// Save and load worlds
+#[cfg(feature = "serde")]
+pub mod serial;
+
+/// Rollback snapshots
+pub mod snapshot;
+
+#[cfg(feature = "2d")]
+use crate::object::tile_map::{Cell, TileId, TileMap};
 use crate::{
-    object::{kinematic_body::KinematicBody, static_body::StaticBody, trigger_area::TriggerArea},
-    Shared,
+    object::{
+        kinematic_body::KinematicBody, moving_platform::MovingPlatform, static_body::StaticBody,
+        trigger_area::TriggerArea, Object,
+    },
+    Mask, Shared,
 };
+use aabb::Aabb;
+use alloc::vec::{Drain, Vec};
+use broadphase::{Broadphase, Bvh};
+use event::Event;
+#[cfg(feature = "2d")]
+use handle::TileMapHandle;
+use handle::{KinematicHandle, PlatformHandle, StaticHandle, TriggerHandle};
+use parry::bounding_volume::BoundingVolume;
 use parry::math::Real;
 use set::Set;
+use step::FixedStep;
 
-/// Define a physics world
+/// Define a physics world.
+/// The objects are partitioned with the given broadphase.
 #[derive(Default)]
-pub struct World<T = (), B = ()> {
+pub struct World<T = (), B = (), P: Broadphase = Bvh> {
     /// Store the list of kinematic bodies
-    kinematic_set: Set<KinematicBody<B>>,
+    kinematic_set: Set<KinematicBody<B>, P>,
 
     /// Store the list of static bodies
-    static_set: Set<StaticBody<B>>,
+    static_set: Set<StaticBody<B>, P>,
+
+    /// Store the list of moving platforms
+    platform_set: Set<MovingPlatform<B>, P>,
 
     /// Store the list of trigger areas
-    trigger_set: Set<TriggerArea<T, B>>,
+    trigger_set: Set<TriggerArea<T, B>, P>,
+
+    /// Store the list of tile maps, their cells are queried directly
+    #[cfg(feature = "2d")]
+    tile_set: Set<TileMap<B>, P>,
 
     /// Epsilon value
     epsilon: Real,
+
+    /// Number of contact resolution iterations
+    iterations: u32,
+
+    /// Margin added around the bounds of the moving objects in the partitions
+    margin: Real,
+
+    /// Speed below which a kinematic body is considered at rest
+    sleep_threshold: Real,
+
+    /// Number of ticks a kinematic body must stay at rest before sleeping
+    sleep_ticks: u32,
+
+    /// Fixed timestep driver
+    fixed_step: FixedStep,
+
+    /// Specify if events should be recorded during updates
+    record_events: bool,
+
+    /// Events recorded since the last drain
+    events: Vec<Event<T, B>>,
 }
 
-impl<B, T> World<T, B> {
+impl<B, T, P: Broadphase> World<T, B, P> {
     /// Create a new world
     pub fn new(epsilon: Real) -> Self {
         Self {
             kinematic_set: Set::default(),
             static_set: Set::default(),
+            platform_set: Set::default(),
             trigger_set: Set::default(),
+            #[cfg(feature = "2d")]
+            tile_set: Set::default(),
             epsilon,
+            iterations: 1,
+            margin: 0.0,
+            sleep_threshold: 0.0,
+            sleep_ticks: 0,
+            fixed_step: FixedStep::default(),
+            record_events: false,
+            events: Vec::new(),
         }
     }
 
@@ -53,82 +140,316 @@ impl<B, T> World<T, B> {
         Self {
             kinematic_set: Set::with_capacity(cap_kinematic),
             static_set: Set::with_capacity(cap_static),
+            platform_set: Set::default(),
             trigger_set: Set::with_capacity(cap_trigger),
+            #[cfg(feature = "2d")]
+            tile_set: Set::default(),
             epsilon,
+            iterations: 1,
+            margin: 0.0,
+            sleep_threshold: 0.0,
+            sleep_ticks: 0,
+            fixed_step: FixedStep::default(),
+            record_events: false,
+            events: Vec::new(),
         }
     }
 }
 
-impl<B, T> World<T, B> {
+impl<B, T, P: Broadphase> World<T, B, P> {
     /// Add a kinematic body to the world
     #[inline]
-    pub fn add_kinematic(&mut self, body: Shared<KinematicBody<B>>) {
-        self.kinematic_set.store(body); // don't update the partition here
+    pub fn add_kinematic(&mut self, body: Shared<KinematicBody<B>>) -> KinematicHandle<B> {
+        self.kinematic_set.store(body) // don't update the partition here
     }
 
     /// Add a static body to the world
     #[inline]
-    pub fn add_static(&mut self, body: Shared<StaticBody<B>>) {
-        self.static_set.add(body);
+    pub fn add_static(&mut self, body: Shared<StaticBody<B>>) -> StaticHandle<B> {
+        self.static_set.add(body)
+    }
+
+    /// Add a moving platform to the world
+    #[inline]
+    pub fn add_platform(&mut self, platform: Shared<MovingPlatform<B>>) -> PlatformHandle<B> {
+        self.platform_set.store(platform) // don't update the partition here
     }
 
     /// Add a trigger area to the world
     #[inline]
-    pub fn add_trigger(&mut self, area: Shared<TriggerArea<T, B>>) {
-        self.trigger_set.add(area);
+    pub fn add_trigger(&mut self, area: Shared<TriggerArea<T, B>>) -> TriggerHandle<T, B> {
+        self.trigger_set.add(area)
     }
-}
 
-impl<B, T> World<T, B> {
-    /// Remove a kinematic body from the world
+    /// Add a tile map to the world
+    #[cfg(feature = "2d")]
     #[inline]
-    pub fn remove_kinematic(&mut self, body: &Shared<KinematicBody<B>>) {
-        self.kinematic_set.quick_remove(body);
+    pub fn add_tile_map(&mut self, map: Shared<TileMap<B>>) -> TileMapHandle<B> {
+        self.tile_set.store(map) // tile maps are not partitioned
     }
+}
 
-    /// Remove a static body from the world
+impl<B, T, P: Broadphase> World<T, B, P> {
+    /// Remove a kinematic body from the world.
+    /// Trigger areas containing the body notice that it left on the next update.
     #[inline]
-    pub fn remove_static(&mut self, body: &Shared<StaticBody<B>>) {
-        self.static_set.clean_remove(body);
+    pub fn remove_kinematic(
+        &mut self,
+        handle: KinematicHandle<B>,
+    ) -> Option<Shared<KinematicBody<B>>> {
+        // the body is not seen anymore, the trigger areas flush it with the other exits
+        self.kinematic_set.clean_remove(handle)
+    }
+
+    /// Remove a static body from the world.
+    /// The sleeping bodies around it are woken up.
+    pub fn remove_static(&mut self, handle: StaticHandle<B>) -> Option<Shared<StaticBody<B>>> {
+        let body = self.static_set.clean_remove(handle)?;
+
+        // the bodies resting on the removed body must notice it is gone
+        self.wake_around(&body.read().aabb());
+        Some(body)
+    }
+
+    /// Remove a moving platform from the world.
+    /// The sleeping bodies around it are woken up.
+    pub fn remove_platform(
+        &mut self,
+        handle: PlatformHandle<B>,
+    ) -> Option<Shared<MovingPlatform<B>>> {
+        let platform = self.platform_set.clean_remove(handle)?;
+
+        // the bodies resting on the removed platform must notice it is gone
+        self.wake_around(&platform.read().aabb());
+        Some(platform)
     }
 
     /// Remove a trigger area from the world
     #[inline]
-    pub fn remove_trigger(&mut self, area: &Shared<TriggerArea<T, B>>) {
-        self.trigger_set.clean_remove(area);
+    pub fn remove_trigger(
+        &mut self,
+        handle: TriggerHandle<T, B>,
+    ) -> Option<Shared<TriggerArea<T, B>>> {
+        self.trigger_set.clean_remove(handle)
+    }
+
+    /// Remove a tile map from the world
+    #[cfg(feature = "2d")]
+    #[inline]
+    pub fn remove_tile_map(&mut self, handle: TileMapHandle<B>) -> Option<Shared<TileMap<B>>> {
+        self.tile_set.quick_remove(handle)
+    }
+}
+
+impl<B, T, P: Broadphase> World<T, B, P> {
+    /// Access a kinematic body of the world
+    #[inline]
+    pub fn get_kinematic(&self, handle: KinematicHandle<B>) -> Option<&Shared<KinematicBody<B>>> {
+        self.kinematic_set.get(handle)
+    }
+
+    /// Access a static body of the world
+    #[inline]
+    pub fn get_static(&self, handle: StaticHandle<B>) -> Option<&Shared<StaticBody<B>>> {
+        self.static_set.get(handle)
+    }
+
+    /// Access a moving platform of the world
+    #[inline]
+    pub fn get_platform(&self, handle: PlatformHandle<B>) -> Option<&Shared<MovingPlatform<B>>> {
+        self.platform_set.get(handle)
+    }
+
+    /// Access a trigger area of the world
+    #[inline]
+    pub fn get_trigger(&self, handle: TriggerHandle<T, B>) -> Option<&Shared<TriggerArea<T, B>>> {
+        self.trigger_set.get(handle)
+    }
+
+    /// Access a tile map of the world
+    #[cfg(feature = "2d")]
+    #[inline]
+    pub fn get_tile_map(&self, handle: TileMapHandle<B>) -> Option<&Shared<TileMap<B>>> {
+        self.tile_set.get(handle)
+    }
+
+    /// Change the tile id of a cell of a tile map and return the previous one.
+    /// The sleeping bodies around the cell are woken up.
+    #[cfg(feature = "2d")]
+    pub fn set_tile(&self, handle: TileMapHandle<B>, cell: Cell, id: TileId) -> Option<TileId> {
+        let mut map = self.tile_set.get(handle)?.write();
+        let previous = map.set_tile_id(cell, id)?;
+
+        // the bodies resting on the cell must notice the change
+        self.wake_around(&Aabb::new(map.cell_aabb(cell), map.layer(), Mask::MAX));
+        Some(previous)
+    }
+
+    /// Wake up the kinematic bodies touching the given bounds
+    fn wake_around(&self, bounds: &Aabb) {
+        let aabb = Aabb::new(
+            bounds.aabb().loosened(self.epsilon),
+            bounds.layer(),
+            Mask::MAX,
+        );
+        self.kinematic_set
+            .query(&aabb, |body| body.write().wake_up());
     }
 }
 
-impl<B, T> World<T, B> {
+impl<B, T, P: Broadphase> World<T, B, P> {
+    /// Number of contact resolution iterations
+    #[inline]
+    pub fn iterations(&self) -> u32 {
+        self.iterations
+    }
+
+    /// Change the number of contact resolution iterations
+    #[inline]
+    pub fn set_iterations(&mut self, iterations: u32) {
+        self.iterations = iterations;
+    }
+
+    /// Margin added around the bounds of the moving objects in the partitions
+    #[inline]
+    pub fn margin(&self) -> Real {
+        self.margin
+    }
+
+    /// Change the margin added around the bounds of the moving objects in the partitions.
+    /// Objects moving within their enlarged bounds are not reinserted on the next update.
+    #[inline]
+    pub fn set_margin(&mut self, margin: Real) {
+        self.margin = margin;
+    }
+
+    /// Speed below which a kinematic body is considered at rest
+    #[inline]
+    pub fn sleep_threshold(&self) -> Real {
+        self.sleep_threshold
+    }
+
+    /// Number of ticks a kinematic body must stay at rest before sleeping
+    #[inline]
+    pub fn sleep_ticks(&self) -> u32 {
+        self.sleep_ticks
+    }
+
+    /// Let the kinematic bodies sleep after staying below the speed threshold
+    /// for the given number of ticks. A count of zero ticks disables sleeping.
+    #[inline]
+    pub fn set_sleeping(&mut self, threshold: Real, ticks: u32) {
+        self.sleep_threshold = threshold;
+        self.sleep_ticks = ticks;
+    }
+}
+
+impl<B, T, P: Broadphase> World<T, B, P> {
+    /// Access the fixed timestep driver
+    #[inline]
+    pub fn fixed_step(&self) -> &FixedStep {
+        &self.fixed_step
+    }
+
+    /// Mutable access the fixed timestep driver
+    #[inline]
+    pub fn fixed_step_mut(&mut self) -> &mut FixedStep {
+        &mut self.fixed_step
+    }
+}
+
+impl<B, T, P: Broadphase> World<T, B, P> {
+    /// Check if events are recorded during updates
+    #[inline]
+    pub fn record_events(&self) -> bool {
+        self.record_events
+    }
+
+    /// Enable or disable the recording of events during updates.
+    /// Recorded events must be drained regularly.
+    #[inline]
+    pub fn set_record_events(&mut self, record: bool) {
+        self.record_events = record;
+    }
+
+    /// Access the events recorded since the last drain
+    #[inline]
+    pub fn events(&self) -> &[Event<T, B>] {
+        &self.events
+    }
+
+    /// Take the events recorded since the last drain
+    #[inline]
+    pub fn drain_events(&mut self) -> Drain<'_, Event<T, B>> {
+        self.events.drain(..)
+    }
+
+    /// Discard the events recorded since the last drain
+    #[inline]
+    pub fn clear_events(&mut self) {
+        self.events.clear();
+    }
+}
+
+impl<B, T, P: Broadphase> World<T, B, P> {
     /// Access the set of kinematic bodies
-    pub fn kinematics(&self) -> &Set<KinematicBody<B>> {
+    pub fn kinematics(&self) -> &Set<KinematicBody<B>, P> {
         &self.kinematic_set
     }
 
     /// Access the set of static bodies
-    pub fn statics(&self) -> &Set<StaticBody<B>> {
+    pub fn statics(&self) -> &Set<StaticBody<B>, P> {
         &self.static_set
     }
 
+    /// Access the set of moving platforms
+    pub fn platforms(&self) -> &Set<MovingPlatform<B>, P> {
+        &self.platform_set
+    }
+
     /// Access the set of trigger areas
-    pub fn triggers(&self) -> &Set<TriggerArea<T, B>> {
+    pub fn triggers(&self) -> &Set<TriggerArea<T, B>, P> {
         &self.trigger_set
     }
+
+    /// Access the set of tile maps
+    #[cfg(feature = "2d")]
+    pub fn tile_maps(&self) -> &Set<TileMap<B>, P> {
+        &self.tile_set
+    }
 }
 
-impl<B, T> World<T, B> {
+impl<B, T, P: Broadphase> World<T, B, P> {
+    /// List the kinematic bodies currently inside the given trigger area
+    pub fn occupants(&self, area: &Shared<TriggerArea<T, B>>) -> Vec<Shared<KinematicBody<B>>> {
+        area.read().occupants().cloned().collect()
+    }
+}
+
+impl<B, T, P: Broadphase> World<T, B, P> {
     /// Mutable access the set of kinematic bodies
-    pub fn kinematics_mut(&mut self) -> &mut Set<KinematicBody<B>> {
+    pub fn kinematics_mut(&mut self) -> &mut Set<KinematicBody<B>, P> {
         &mut self.kinematic_set
     }
 
     /// Mutable access the set of static bodies
-    pub fn statics_mut(&mut self) -> &mut Set<StaticBody<B>> {
+    pub fn statics_mut(&mut self) -> &mut Set<StaticBody<B>, P> {
         &mut self.static_set
     }
 
+    /// Mutable access the set of moving platforms
+    pub fn platforms_mut(&mut self) -> &mut Set<MovingPlatform<B>, P> {
+        &mut self.platform_set
+    }
+
     /// Mutable access the set of trigger areas
-    pub fn triggers_mut(&mut self) -> &mut Set<TriggerArea<T, B>> {
+    pub fn triggers_mut(&mut self) -> &mut Set<TriggerArea<T, B>, P> {
         &mut self.trigger_set
     }
+
+    /// Mutable access the set of tile maps
+    #[cfg(feature = "2d")]
+    pub fn tile_maps_mut(&mut self) -> &mut Set<TileMap<B>, P> {
+        &mut self.tile_set
+    }
 }
@@ -6,9 +6,12 @@ use parry::{
     math::{Point, Real},
     query::Ray,
 };
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 /// Axis-Aligned Bounding Box (AABB)
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Aabb {
     /// Parry's Axis-Aligned Bounding Box
     aabb: p::Aabb,
@@ -28,10 +31,37 @@ impl Aabb {
     }
 
     /// Create a new AABB from a ray
-    pub fn from_ray(ray: &Ray, max_time_of_impact: Real, mask: Mask) -> Self {
-        let (mins, maxs) = ray.origin.coords.inf_sup(&(ray.dir * max_time_of_impact));
+    pub fn from_ray(ray: &Ray, max_time_of_impact: Real, layer: Mask, mask: Mask) -> Self {
+        let (mins, maxs) = ray
+            .origin
+            .coords
+            .inf_sup(&ray.point_at(max_time_of_impact).coords);
         let aabb = p::Aabb::new(Point::from(mins), Point::from(maxs));
-        Self::new(aabb, Mask::MAX, mask)
+        Self::new(aabb, layer, mask)
+    }
+
+    /// Create a new AABB enclosing a single point
+    #[inline]
+    pub fn from_point(point: &Point<Real>, layer: Mask, mask: Mask) -> Self {
+        Self::new(p::Aabb::new(*point, *point), layer, mask)
+    }
+
+    /// Check if this AABB encloses the other one on the same layer and mask
+    #[inline]
+    pub fn contains(&self, other: &Self) -> bool {
+        self.layer == other.layer
+            && self.mask == other.mask
+            && p::BoundingVolume::contains(&self.aabb, &other.aabb)
+    }
+
+    /// Grow this AABB by the given margin on every side
+    #[inline]
+    pub fn loosened(&self, margin: Real) -> Self {
+        Self::new(
+            p::BoundingVolume::loosened(&self.aabb, margin),
+            self.layer,
+            self.mask,
+        )
     }
 
     /// Access the Parry's Axis-Aligned Bounding Box
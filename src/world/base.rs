@@ -1,31 +1,77 @@
-use super::World;
-use crate::{
-    object::{
-        collides, intersects, kinematic_body::KinematicBody, static_body::StaticBody,
-        trigger_area::TriggerArea, Object,
-    },
-    world::aabb::Aabb,
-    Mask, Shared,
+use super::{
+    broadphase::{Broadphase, Partition},
+    event::Event,
+    World,
 };
-use parry::{
-    math::Real,
-    query::{Ray, RayIntersection, ShapeCastOptions},
+#[cfg(feature = "2d")]
+use crate::object::cast_shapes;
+use crate::object::{
+    collides, intersects,
+    kinematic_body::KinematicBody,
+    moving_platform::MovingPlatform,
+    static_body::StaticBody,
+    trigger_area::{Overlap, TriggerArea},
+    Object,
 };
+#[cfg(feature = "2d")]
+use bvh_arena::BoundingVolume;
+use nalgebra_glm::is_null;
+#[cfg(feature = "2d")]
+use parry::math::Vector;
+use parry::{math::Real, query::ShapeCastOptions};
 
-impl<B, T> World<T, B>
+impl<B, T, P> World<T, B, P>
 where
     B: Clone,
     T: Clone,
+    P: Broadphase,
 {
+    /// Advance the world by the frame time using fixed ticks.
+    /// Return the interpolation factor to use for rendering.
+    pub fn step(&mut self, frame_time: Real) -> Real {
+        let ticks = self.fixed_step.advance(frame_time);
+        let tick = self.fixed_step.tick();
+        for _ in 0..ticks {
+            self.update(tick);
+        }
+        self.fixed_step.alpha()
+    }
+
     /// Update the state of the world
     pub fn update(&mut self, delta_time: Real) {
         // Options for kinematic bodies collisions
         let options = ShapeCastOptions::with_max_time_of_impact(delta_time);
 
+        // Move the platforms first so that they can carry the kinematic bodies
+        for platform in self.platform_set.iter_mut() {
+            platform.write().pre_update(delta_time);
+        }
+        self.platform_set.refit(self.margin);
+
         // Check collisions between kinematic bodies and static bodies
-        for kinematic in self.kinematic_set.iter_mut() {
+        for kinematic in self.kinematic_set.iter() {
             // prepare the  kinematic body for current update
             let mut mut_kine = kinematic.write();
+            if mut_kine.is_sleeping() {
+                // a velocity moving the body past its contacts wakes it up,
+                // the part cancelled by the surfaces it rests on, such as gravity, does not
+                let motion = mut_kine.velocity() * delta_time;
+                let mut reached = motion.norm() > self.sleep_threshold * delta_time
+                    && self.resolved_motion(kinematic, &mut_kine, &motion).norm()
+                        > self.sleep_threshold * delta_time;
+
+                // a moving platform reaching the body wakes it up
+                self.platform_set
+                    .partition
+                    .for_each_overlaps(&mut_kine.aabb(), |platform| {
+                        reached |= !is_null(&platform.read().displacement(), self.epsilon);
+                    });
+                if !reached {
+                    mut_kine.rest();
+                    continue;
+                }
+                mut_kine.wake_up();
+            }
             mut_kine.pre_update(delta_time);
             let aabb = mut_kine.aabb();
 
@@ -38,14 +84,84 @@ where
                     let astatic = astatic.read();
                     if let Some(hit) =
                         collides::<KinematicBody<B>, StaticBody<B>>(&mut_kine, &astatic, options)
+                        && astatic.blocks(&mut_kine, mut_kine.isometry(), &hit, self.epsilon)
                     {
-                        mut_kine.add_contact(hit, None, astatic.payload().clone());
+                        mut_kine.add_contact(hit, &*astatic, None, astatic.payload().clone());
+                        if self.record_events {
+                            self.events.push(Event::Static {
+                                hit,
+                                kinematic: mut_kine.payload().clone(),
+                                other: astatic.payload().clone(),
+                            });
+                        }
+                    }
+                });
+
+            // check for collisions with the tiles of the tile maps
+            #[cfg(feature = "2d")]
+            for map in self.tile_set.iter() {
+                let map = map.read();
+                if !aabb.overlaps(&map.aabb()) {
+                    continue;
+                }
+                map.for_each_tile(aabb.aabb(), |cell, shape, isometry| {
+                    // ignore the faces buried between two tiles
+                    if let Some(hit) = cast_shapes(
+                        mut_kine.isometry(),
+                        &mut_kine.velocity(),
+                        mut_kine.shape(),
+                        isometry,
+                        &Vector::zeros(),
+                        shape.as_ref(),
+                        options,
+                    ) && !map.is_internal_edge(cell, &hit.normal2, self.epsilon)
+                    {
+                        mut_kine.add_fixed_contact(
+                            hit,
+                            shape.clone(),
+                            *isometry,
+                            map.payload().clone(),
+                        );
+                        if self.record_events {
+                            self.events.push(Event::Tile {
+                                hit,
+                                kinematic: mut_kine.payload().clone(),
+                                other: map.payload().clone(),
+                                cell,
+                            });
+                        }
+                    }
+                });
+            }
+
+            // check for collisions with moving platforms
+            self.platform_set
+                .partition
+                .for_each_overlaps(&aabb, |platform| {
+                    let platform = platform.read();
+                    if let Some(hit) = collides::<KinematicBody<B>, MovingPlatform<B>>(
+                        &mut_kine, &platform, options,
+                    ) {
+                        // the platform carries the bodies standing on it
+                        let carried = platform.is_carrying(mut_kine.isometry(), &hit);
+                        if carried {
+                            mut_kine.carry(platform.displacement());
+                        }
+                        mut_kine.add_contact(hit, &*platform, None, platform.payload().clone());
+                        if self.record_events {
+                            self.events.push(Event::Platform {
+                                hit,
+                                kinematic: mut_kine.payload().clone(),
+                                other: platform.payload().clone(),
+                                carried,
+                            });
+                        }
                     }
                 });
         }
 
         // Check collisions inbetween kinematic bodies
-        self.kinematic_set.repartition();
+        self.kinematic_set.refit(self.margin);
         self.kinematic_set
             .partition
             .for_each_overlaping_pair(|kinematic1, kinematic2| {
@@ -53,28 +169,63 @@ where
                 let mut mut_k1 = kinematic1.write();
                 let mut mut_k2 = kinematic2.write();
 
+                // sleeping bodies don't collide with each other
+                if mut_k1.is_sleeping() && mut_k2.is_sleeping() {
+                    return;
+                }
+
                 if let Some(hit) =
                     collides::<KinematicBody<B>, KinematicBody<B>>(&mut_k1, &mut_k2, options)
                 {
-                    mut_k1.add_contact(hit, Some(mut_k2.weight()), mut_k2.payload().clone());
-                    mut_k2.add_contact(
-                        hit.swapped(),
-                        Some(mut_k1.weight()),
-                        mut_k1.payload().clone(),
-                    );
+                    // a sleeping body wakes up when touched
+                    mut_k1.wake_up();
+                    mut_k2.wake_up();
+
+                    let (w1, w2) = (mut_k1.weight(), mut_k2.weight());
+                    let (p1, p2) = (mut_k1.payload().clone(), mut_k2.payload().clone());
+                    if self.record_events {
+                        self.events.push(Event::Kinematic {
+                            hit,
+                            kinematic1: p1.clone(),
+                            kinematic2: p2.clone(),
+                        });
+                    }
+                    mut_k1.add_contact(hit, &*mut_k2, Some(w2), p2);
+                    mut_k2.add_contact(hit.swapped(), &*mut_k1, Some(w1), p1);
                 }
             });
 
         // resolve actual motion using accumulated collision hits
-        for kinematic in self.kinematic_set.iter_mut() {
-            kinematic.write().apply_contacts(delta_time, self.epsilon);
+        for kinematic in self.kinematic_set.iter() {
+            let mut mut_kine = kinematic.write();
+            if mut_kine.is_sleeping() {
+                continue;
+            }
+            mut_kine.apply_contacts(delta_time, self.epsilon, self.iterations);
+            self.move_and_slide(kinematic, &mut mut_kine, delta_time);
+
+            // climb on top of the small obstacles blocking the body
+            self.step_up(kinematic, &mut mut_kine, delta_time);
+            self.snap_to_ground(kinematic, &mut mut_kine);
+
+            // put the body to sleep once it stayed at rest long enough
+            mut_kine.update_sleep(self.sleep_threshold, self.sleep_ticks, delta_time);
         }
 
         // Check intersections between kinematic bodies and trigger areas
-        for kinematic in self.kinematic_set.iter_mut() {
+        for (handle, kinematic) in self.kinematic_set.entries() {
             // mutable access to the kinematic body
             let mut mut_kine = kinematic.write();
             let aabb = mut_kine.aabb();
+
+            // sleeping bodies stay in the trigger areas they occupy
+            if mut_kine.is_sleeping() {
+                self.trigger_set
+                    .partition
+                    .for_each_overlaps(&aabb, |trigger| trigger.write().keep(handle));
+                continue;
+            }
+
             // check for intersections with trigger areas
             self.trigger_set
                 .partition
@@ -83,98 +234,39 @@ where
                     if intersects::<KinematicBody<B>, TriggerArea<T, B>>(&mut_kine, &trigger) {
                         // the kinematic body intersect with this trigger area
                         // call the callback of the trigger on both
-                        trigger.on_overlap(&mut mut_kine)
+                        let overlap = trigger.overlap(handle, kinematic, &mut mut_kine);
+                        if self.record_events {
+                            self.events.push(Event::Trigger {
+                                overlap,
+                                trigger: trigger.payload().clone(),
+                                kinematic: mut_kine.payload().clone(),
+                            });
+                        }
                     }
                 });
         }
-    }
-}
 
-impl<B, T> World<T, B> {
-    /// Perform a raycast with the static and/or kinematic bodies in this world
-    pub fn raycast(
-        &self,
-        ray: &Ray,
-        max_time_of_impact: Real,
-        mask: Mask,
-        hit_statics: bool,
-        hit_kinematics: bool,
-    ) -> RayResult<B> {
-        // Define the AABB around the ray
-        let aabb = Aabb::from_ray(ray, max_time_of_impact, mask);
-
-        // Try to find the best candidate
-        let mut found = RayResult::None;
-        let mut time = Real::MAX;
-
-        // Check static bodies
-        if hit_statics {
-            self.static_set.partition.for_each_overlaps(&aabb, |body| {
-                let b = body.read();
-                if let Some(hit) =
-                    b.shape()
-                        .cast_ray_and_get_normal(b.isometry(), ray, max_time_of_impact, true)
-                {
-                    // if the hit is closer to the origin, replace the previous result
-                    if hit.time_of_impact < time {
-                        time = hit.time_of_impact;
-                        found = RayResult::Static {
-                            hit,
-                            object: body.clone(),
-                        };
-                    }
-                }
-            });
-        }
-
-        // Check kinematic bodies
-        if hit_kinematics {
-            self.kinematic_set
-                .partition
-                .for_each_overlaps(&aabb, |body| {
-                    let b = body.read();
-                    if let Some(hit) = b.shape().cast_ray_and_get_normal(
-                        b.isometry(),
-                        ray,
-                        max_time_of_impact,
-                        true,
-                    ) {
-                        // if the hit is closer to the origin, replace the previous result
-                        if hit.time_of_impact < time {
-                            time = hit.time_of_impact;
-                            found = RayResult::Kinematic {
-                                hit,
-                                object: body.clone(),
-                            };
-                        }
+        // Notify the bodies which left the trigger areas, wake up the ones asked for
+        for trigger in self.trigger_set.iter() {
+            let mut trigger = trigger.write();
+            if trigger.take_wake_request() {
+                self.kinematic_set.query(&trigger.aabb(), |body| {
+                    let mut body = body.write();
+                    if intersects::<TriggerArea<T, B>, KinematicBody<B>>(&trigger, &body) {
+                        body.wake_up();
                     }
                 });
+            }
+            let left = trigger.flush_exits();
+            if self.record_events {
+                for body in left {
+                    self.events.push(Event::Trigger {
+                        overlap: Overlap::Exit,
+                        trigger: trigger.payload().clone(),
+                        kinematic: body.read().payload().clone(),
+                    });
+                }
+            }
         }
-
-        found
     }
 }
-
-/// Return data relative to the object that have been hit by the raycast
-pub enum RayResult<P> {
-    /// No object has been hit
-    None,
-
-    /// The object hit is a static body
-    Static {
-        /// Ray intersection data
-        hit: RayIntersection,
-
-        /// Reference to the object
-        object: Shared<StaticBody<P>>,
-    },
-
-    /// The object hit is a kinematic body
-    Kinematic {
-        /// Ray intersection data
-        hit: RayIntersection,
-
-        /// Reference to the object
-        object: Shared<KinematicBody<P>>,
-    },
-}
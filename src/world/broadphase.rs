@@ -0,0 +1,300 @@
+//! Broadphase structures partitioning the objects of a set
+
+/// Uniform grid broadphase
+pub mod grid;
+
+/// Sweep-and-prune broadphase
+pub mod sweep;
+
+use super::aabb::Aabb;
+use bvh_arena::VolumeHandle;
+
+/// Family of broadphase structures a set can be partitioned with
+pub trait Broadphase {
+    /// Structure partitioning the data of type `D`
+    type Partition<D>: Partition<D>;
+}
+
+/// Spatial structure finding the overlapping bounding volumes.
+/// Volumes only overlap if their layers and masks match.
+pub trait Partition<D>: Default {
+    /// Handle to a volume inserted in the partition
+    type Handle: Copy;
+
+    /// Insert a bounding volume along with its data
+    fn insert(&mut self, data: D, volume: Aabb) -> Self::Handle;
+
+    /// Remove a bounding volume and return its data
+    fn remove(&mut self, handle: Self::Handle) -> Option<D>;
+
+    /// Remove every bounding volume
+    fn clear(&mut self);
+
+    /// Bring the structure up to date after a batch of insertions and removals.
+    /// It must be called before querying the structure again.
+    #[inline]
+    fn update(&mut self) {}
+
+    /// Report the data of each volume overlapping the given one
+    fn for_each_overlaps(&self, volume: &Aabb, on_overlap: impl FnMut(&D));
+
+    /// Report each pair of overlapping volumes
+    fn for_each_overlaping_pair(&self, on_overlaping_pair: impl FnMut(&D, &D));
+}
+
+/// Bounding volume hierarchy broadphase, suited to most worlds
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Bvh;
+
+impl Broadphase for Bvh {
+    type Partition<D> = bvh_arena::Bvh<D, Aabb>;
+}
+
+impl<D> Partition<D> for bvh_arena::Bvh<D, Aabb> {
+    type Handle = VolumeHandle;
+
+    #[inline]
+    fn insert(&mut self, data: D, volume: Aabb) -> Self::Handle {
+        bvh_arena::Bvh::insert(self, data, volume)
+    }
+
+    #[inline]
+    fn remove(&mut self, handle: Self::Handle) -> Option<D> {
+        bvh_arena::Bvh::remove(self, handle)
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        bvh_arena::Bvh::clear(self);
+    }
+
+    #[inline]
+    fn for_each_overlaps(&self, volume: &Aabb, on_overlap: impl FnMut(&D)) {
+        bvh_arena::Bvh::for_each_overlaps(self, volume, on_overlap);
+    }
+
+    #[inline]
+    fn for_each_overlaping_pair(&self, on_overlaping_pair: impl FnMut(&D, &D)) {
+        bvh_arena::Bvh::for_each_overlaping_pair(self, on_overlaping_pair);
+    }
+}
+
+/// Uniform grid broadphase, suited to objects of similar sizes
+#[derive(Debug, Default, Clone, Copy)]
+pub struct UniformGrid;
+
+impl Broadphase for UniformGrid {
+    type Partition<D> = grid::GridPartition<D>;
+}
+
+/// Sweep-and-prune broadphase, suited to objects spread along one axis
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SweepAndPrune;
+
+impl Broadphase for SweepAndPrune {
+    type Partition<D> = sweep::SweepPartition<D>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{grid::GridPartition, sweep::SweepPartition, Partition};
+    use crate::{world::aabb::Aabb, Mask};
+    use alloc::vec::Vec;
+    use bvh_arena::BoundingVolume;
+    use parry::{
+        bounding_volume as p,
+        math::{Point, Real},
+    };
+
+    /// Small deterministic generator, good enough to scatter volumes
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn real(&mut self, min: Real, max: Real) -> Real {
+            min + (max - min) * (self.next() % 10_000) as Real / 10_000.0
+        }
+
+        fn volume(&mut self) -> Aabb {
+            let mins = Point::from(core::array::from_fn(|_| self.real(-20.0, 20.0)));
+            let size = Point::from(core::array::from_fn(|_| self.real(0.1, 4.0)));
+            let layer = 1 << (self.next() % 3);
+            let mask = 1 << (self.next() % 3) | 1;
+            Aabb::new(
+                p::Aabb::new(mins, mins + size.coords),
+                layer as Mask,
+                mask as Mask,
+            )
+        }
+    }
+
+    /// Partition under test along with the handles of its volumes
+    struct Tested<P: Partition<usize>> {
+        partition: P,
+        handles: Vec<Option<P::Handle>>,
+    }
+
+    impl<P: Partition<usize>> Tested<P> {
+        fn new(partition: P) -> Self {
+            Self {
+                partition,
+                handles: Vec::new(),
+            }
+        }
+
+        fn insert(&mut self, id: usize, volume: Aabb) {
+            self.handles.resize(self.handles.len().max(id + 1), None);
+            self.handles[id] = Some(self.partition.insert(id, volume));
+        }
+
+        fn remove(&mut self, id: usize) {
+            let handle = self.handles[id].take().unwrap();
+            assert_eq!(self.partition.remove(handle), Some(id));
+        }
+
+        fn overlaps(&self, volume: &Aabb) -> Vec<usize> {
+            let mut found = Vec::new();
+            self.partition
+                .for_each_overlaps(volume, |id| found.push(*id));
+            found.sort_unstable();
+            found
+        }
+
+        fn pairs(&self) -> Vec<(usize, usize)> {
+            let mut found = Vec::new();
+            self.partition
+                .for_each_overlaping_pair(|a, b| found.push((*a.min(b), *a.max(b))));
+            found.sort_unstable();
+            found
+        }
+    }
+
+    /// Compare the partitions to the BVH, and the BVH to a brute force search
+    fn check(
+        volumes: &[Option<Aabb>],
+        bvh: &Tested<bvh_arena::Bvh<usize, Aabb>>,
+        grid: &Tested<GridPartition<usize>>,
+        sweep: &Tested<SweepPartition<usize>>,
+        rng: &mut Rng,
+    ) {
+        let live = || {
+            volumes
+                .iter()
+                .enumerate()
+                .filter_map(|(id, volume)| Some((id, volume.as_ref()?)))
+        };
+
+        for _ in 0..50 {
+            let query = rng.volume();
+            let expected = bvh.overlaps(&query);
+            let brute: Vec<_> = live()
+                .filter(|(_, volume)| volume.overlaps(&query))
+                .map(|(id, _)| id)
+                .collect();
+            assert_eq!(expected, brute);
+            assert_eq!(grid.overlaps(&query), expected);
+            assert_eq!(sweep.overlaps(&query), expected);
+        }
+
+        let expected = bvh.pairs();
+        let mut brute = Vec::new();
+        for (id1, volume1) in live() {
+            for (id2, volume2) in live().skip_while(|(id2, _)| *id2 <= id1) {
+                if volume1.overlaps(volume2) {
+                    brute.push((id1, id2));
+                }
+            }
+        }
+        assert!(!expected.is_empty());
+        assert_eq!(expected, brute);
+        assert_eq!(grid.pairs(), expected);
+        assert_eq!(sweep.pairs(), expected);
+    }
+
+    /// Apply the same changes to every partition and compare them
+    fn compare(mut grid: Tested<GridPartition<usize>>, mut sweep: Tested<SweepPartition<usize>>) {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        let mut bvh = Tested::new(bvh_arena::Bvh::<usize, Aabb>::default());
+        let mut volumes: Vec<Option<Aabb>> = Vec::new();
+
+        // scatter the volumes
+        for id in 0..300 {
+            let volume = rng.volume();
+            volumes.push(Some(volume));
+            bvh.insert(id, volume);
+            grid.insert(id, volume);
+            sweep.insert(id, volume);
+        }
+        grid.partition.update();
+        sweep.partition.update();
+        check(&volumes, &bvh, &grid, &sweep, &mut rng);
+
+        for _ in 0..5 {
+            // move every volume a little, as a refit does
+            for (id, volume) in volumes.iter_mut().enumerate() {
+                let Some(volume) = volume.as_mut() else {
+                    continue;
+                };
+                let offset = Point::from(core::array::from_fn(|_| rng.real(-0.5, 0.5)));
+                *volume = Aabb::new(
+                    p::Aabb::new(
+                        volume.aabb().mins + offset.coords,
+                        volume.aabb().maxs + offset.coords,
+                    ),
+                    volume.layer(),
+                    volume.mask(),
+                );
+                bvh.remove(id);
+                grid.remove(id);
+                sweep.remove(id);
+                bvh.insert(id, *volume);
+                grid.insert(id, *volume);
+                sweep.insert(id, *volume);
+            }
+
+            // remove some volumes for good and add new ones
+            for _ in 0..20 {
+                let id = rng.next() as usize % volumes.len();
+                if volumes[id].take().is_some() {
+                    bvh.remove(id);
+                    grid.remove(id);
+                    sweep.remove(id);
+                }
+            }
+            for _ in 0..10 {
+                let id = volumes.len();
+                let volume = rng.volume();
+                volumes.push(Some(volume));
+                bvh.insert(id, volume);
+                grid.insert(id, volume);
+                sweep.insert(id, volume);
+            }
+
+            grid.partition.update();
+            sweep.partition.update();
+            check(&volumes, &bvh, &grid, &sweep, &mut rng);
+        }
+    }
+
+    #[test]
+    fn partitions_match_bvh() {
+        compare(
+            Tested::new(GridPartition::default()),
+            Tested::new(SweepPartition::default()),
+        );
+    }
+
+    #[test]
+    fn partitions_match_bvh_with_other_settings() {
+        compare(
+            Tested::new(GridPartition::new(0.5)),
+            Tested::new(SweepPartition::new(1)),
+        );
+    }
+}
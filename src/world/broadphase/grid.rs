@@ -0,0 +1,291 @@
+//! Uniform grid splitting the space into cells of the same size
+
+use super::Partition;
+use crate::world::aabb::Aabb;
+use alloc::{collections::BTreeMap, vec::Vec};
+use bvh_arena::BoundingVolume;
+use parry::{
+    math::{Point, Real, DIM},
+    na::ComplexField,
+};
+
+/// Default size of the cells
+const DEFAULT_CELL_SIZE: Real = 1.0;
+
+/// Maximum number of cells a volume can cover.
+/// Larger volumes are tested against every other volume instead.
+const MAX_CELLS: u64 = 64;
+
+/// Coordinates of a cell in the grid
+type Cell = [i32; DIM];
+
+/// Volume stored in the grid
+struct Entry<D> {
+    /// Data attached to the volume
+    data: D,
+
+    /// Bounding volume
+    volume: Aabb,
+
+    /// Range of cells covered by the volume, none if it is too large
+    cells: Option<(Cell, Cell)>,
+}
+
+/// Uniform grid partitioning the volumes into cells
+pub struct GridPartition<D> {
+    /// Size of the cells along each axis
+    cell_size: Real,
+
+    /// Volumes stored in the grid
+    entries: Vec<Option<Entry<D>>>,
+
+    /// Indices of the empty entries
+    free: Vec<usize>,
+
+    /// Indices of the volumes covering each occupied cell
+    cells: BTreeMap<Cell, Vec<usize>>,
+
+    /// Indices of the volumes covering too many cells
+    oversized: Vec<usize>,
+}
+
+impl<D> Default for GridPartition<D> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(DEFAULT_CELL_SIZE)
+    }
+}
+
+impl<D> GridPartition<D> {
+    /// Create an empty grid with the given cell size
+    #[inline]
+    pub fn new(cell_size: Real) -> Self {
+        Self {
+            cell_size,
+            entries: Vec::new(),
+            free: Vec::new(),
+            cells: BTreeMap::new(),
+            oversized: Vec::new(),
+        }
+    }
+
+    /// Size of the cells along each axis
+    #[inline]
+    pub fn cell_size(&self) -> Real {
+        self.cell_size
+    }
+
+    /// Change the size of the cells and redistribute the volumes
+    pub fn set_cell_size(&mut self, cell_size: Real) {
+        self.cell_size = cell_size;
+        self.cells.clear();
+        self.oversized.clear();
+        for index in 0..self.entries.len() {
+            if let Some(entry) = self.entries[index].as_ref() {
+                let volume = entry.volume;
+                self.link(index, &volume);
+            }
+        }
+    }
+
+    /// Coordinates of the cell containing the point
+    fn cell(&self, point: &Point<Real>) -> Cell {
+        core::array::from_fn(|axis| ComplexField::floor(point[axis] / self.cell_size) as i32)
+    }
+
+    /// Range of cells covered by the volume
+    #[inline]
+    fn range(&self, volume: &Aabb) -> (Cell, Cell) {
+        (
+            self.cell(&volume.aabb().mins),
+            self.cell(&volume.aabb().maxs),
+        )
+    }
+
+    /// Register the volume of an entry in the cells it covers
+    fn link(&mut self, index: usize, volume: &Aabb) {
+        let (lo, hi) = self.range(volume);
+        let cells = if count_cells(&lo, &hi) > MAX_CELLS {
+            self.oversized.push(index);
+            None
+        } else {
+            for_each_cell(&lo, &hi, |cell| {
+                self.cells.entry(cell).or_default().push(index)
+            });
+            Some((lo, hi))
+        };
+        if let Some(entry) = self.entries[index].as_mut() {
+            entry.cells = cells;
+        }
+    }
+
+    /// Access the entry at the given index
+    #[inline]
+    fn entry(&self, index: usize) -> Option<&Entry<D>> {
+        self.entries.get(index).and_then(Option::as_ref)
+    }
+}
+
+impl<D> Partition<D> for GridPartition<D> {
+    type Handle = usize;
+
+    fn insert(&mut self, data: D, volume: Aabb) -> Self::Handle {
+        let entry = Entry {
+            data,
+            volume,
+            cells: None,
+        };
+        let index = if let Some(index) = self.free.pop() {
+            self.entries[index] = Some(entry);
+            index
+        } else {
+            self.entries.push(Some(entry));
+            self.entries.len() - 1
+        };
+        self.link(index, &volume);
+        index
+    }
+
+    fn remove(&mut self, handle: Self::Handle) -> Option<D> {
+        let entry = self.entries.get_mut(handle)?.take()?;
+        self.free.push(handle);
+
+        // unregister the volume from the cells it covers
+        if let Some((lo, hi)) = entry.cells {
+            for_each_cell(&lo, &hi, |cell| {
+                if let Some(list) = self.cells.get_mut(&cell) {
+                    list.retain(|&index| index != handle);
+                    if list.is_empty() {
+                        self.cells.remove(&cell);
+                    }
+                }
+            });
+        } else {
+            self.oversized.retain(|&index| index != handle);
+        }
+        Some(entry.data)
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.free.clear();
+        self.cells.clear();
+        self.oversized.clear();
+    }
+
+    fn for_each_overlaps(&self, volume: &Aabb, mut on_overlap: impl FnMut(&D)) {
+        let (lo, hi) = self.range(volume);
+
+        // the large volumes are not registered in the cells
+        for &index in &self.oversized {
+            if let Some(entry) = self.entry(index)
+                && entry.volume.overlaps(volume)
+            {
+                on_overlap(&entry.data);
+            }
+        }
+
+        // a volume covering several cells is only reported in the first cell it shares with the query
+        let mut visit = |cell: &Cell, list: &Vec<usize>| {
+            for &index in list {
+                if let Some(entry) = self.entry(index)
+                    && let Some((first, _)) = entry.cells
+                    && (0..DIM).all(|axis| cell[axis] == first[axis].max(lo[axis]))
+                    && entry.volume.overlaps(volume)
+                {
+                    on_overlap(&entry.data);
+                }
+            }
+        };
+
+        // visit the occupied cells directly if there are fewer of them
+        if count_cells(&lo, &hi) > self.cells.len() as u64 {
+            for (cell, list) in &self.cells {
+                if (0..DIM).all(|axis| lo[axis] <= cell[axis] && cell[axis] <= hi[axis]) {
+                    visit(cell, list);
+                }
+            }
+        } else {
+            for_each_cell(&lo, &hi, |cell| {
+                if let Some(list) = self.cells.get(&cell) {
+                    visit(&cell, list);
+                }
+            });
+        }
+    }
+
+    fn for_each_overlaping_pair(&self, mut on_overlaping_pair: impl FnMut(&D, &D)) {
+        for (cell, list) in &self.cells {
+            for (i, &index1) in list.iter().enumerate() {
+                let Some(entry1) = self.entry(index1) else {
+                    continue;
+                };
+                for &index2 in &list[i + 1..] {
+                    let Some(entry2) = self.entry(index2) else {
+                        continue;
+                    };
+
+                    // only report the pair in the first cell both volumes share
+                    let mins = entry1.volume.aabb().mins.sup(&entry2.volume.aabb().mins);
+                    if self.cell(&mins) == *cell && entry1.volume.overlaps(&entry2.volume) {
+                        on_overlaping_pair(&entry1.data, &entry2.data);
+                    }
+                }
+            }
+        }
+
+        // test the large volumes against every other volume
+        for &index1 in &self.oversized {
+            let Some(entry1) = self.entry(index1) else {
+                continue;
+            };
+            for (index2, entry2) in self.entries.iter().enumerate() {
+                let Some(entry2) = entry2 else {
+                    continue;
+                };
+
+                // pairs of large volumes are reported once
+                if index2 == index1 || (entry2.cells.is_none() && index2 < index1) {
+                    continue;
+                }
+                if entry1.volume.overlaps(&entry2.volume) {
+                    on_overlaping_pair(&entry1.data, &entry2.data);
+                }
+            }
+        }
+    }
+}
+
+/// Number of cells in the range
+fn count_cells(lo: &Cell, hi: &Cell) -> u64 {
+    (0..DIM).fold(1u64, |count, axis| {
+        let length = (hi[axis] as i64 - lo[axis] as i64 + 1).max(0) as u64;
+        count.saturating_mul(length)
+    })
+}
+
+/// Visit every cell in the range
+fn for_each_cell(lo: &Cell, hi: &Cell, mut on_cell: impl FnMut(Cell)) {
+    if (0..DIM).any(|axis| lo[axis] > hi[axis]) {
+        return;
+    }
+
+    let mut cell = *lo;
+    loop {
+        on_cell(cell);
+
+        // move to the next cell, carrying over to the next axes
+        let mut axis = 0;
+        loop {
+            if axis == DIM {
+                return;
+            }
+            if cell[axis] < hi[axis] {
+                cell[axis] += 1;
+                break;
+            }
+            cell[axis] = lo[axis];
+            axis += 1;
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! Sweep-and-prune keeping the volumes sorted along one axis
+
+use super::Partition;
+use crate::world::aabb::Aabb;
+use alloc::vec::Vec;
+use bvh_arena::BoundingVolume;
+use parry::math::{Real, DIM};
+
+/// Volume stored in the partition
+struct Entry<D> {
+    /// Data attached to the volume
+    data: D,
+
+    /// Bounding volume
+    volume: Aabb,
+}
+
+/// Sweep-and-prune partitioning the volumes along one axis.
+/// Inserted and removed volumes are only sorted in place on `update`,
+/// so a refit moving each volume a little costs a near-linear insertion sort.
+pub struct SweepPartition<D> {
+    /// Axis along which the volumes are sorted
+    axis: usize,
+
+    /// Volumes stored in the partition
+    entries: Vec<Option<Entry<D>>>,
+
+    /// Specify for each entry if its index is in the sorted list
+    listed: Vec<bool>,
+
+    /// Indices of the empty entries
+    free: Vec<usize>,
+
+    /// Indices of the volumes sorted by their lower bound along the axis.
+    /// Between updates, it also holds removed and unsorted volumes.
+    sorted: Vec<usize>,
+
+    /// Number of indices appended to the sorted list since the last update
+    appended: usize,
+}
+
+impl<D> Default for SweepPartition<D> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl<D> SweepPartition<D> {
+    /// Create an empty partition sorting the volumes along the given axis
+    #[inline]
+    pub fn new(axis: usize) -> Self {
+        assert!(axis < DIM, "the sweep axis must be a valid dimension");
+        Self {
+            axis,
+            entries: Vec::new(),
+            listed: Vec::new(),
+            free: Vec::new(),
+            sorted: Vec::new(),
+            appended: 0,
+        }
+    }
+
+    /// Axis along which the volumes are sorted
+    #[inline]
+    pub fn axis(&self) -> usize {
+        self.axis
+    }
+
+    /// Change the axis along which the volumes are sorted
+    pub fn set_axis(&mut self, axis: usize) {
+        assert!(axis < DIM, "the sweep axis must be a valid dimension");
+        self.axis = axis;
+        let mut sorted = core::mem::take(&mut self.sorted);
+        sorted.sort_by(|&a, &b| self.lower(a).total_cmp(&self.lower(b)));
+        self.sorted = sorted;
+    }
+
+    /// Access the entry at the given index
+    #[inline]
+    fn entry(&self, index: usize) -> Option<&Entry<D>> {
+        self.entries.get(index).and_then(Option::as_ref)
+    }
+
+    /// Lower bound of a volume along the axis
+    #[inline]
+    fn lower(&self, index: usize) -> Real {
+        self.entry(index)
+            .map_or(Real::MAX, |entry| entry.volume.aabb().mins[self.axis])
+    }
+
+    /// Upper bound of a volume along the axis
+    #[inline]
+    fn upper(&self, index: usize) -> Real {
+        self.entry(index)
+            .map_or(Real::MIN, |entry| entry.volume.aabb().maxs[self.axis])
+    }
+}
+
+impl<D> Partition<D> for SweepPartition<D> {
+    type Handle = usize;
+
+    fn insert(&mut self, data: D, volume: Aabb) -> Self::Handle {
+        let entry = Entry { data, volume };
+        let index = if let Some(index) = self.free.pop() {
+            self.entries[index] = Some(entry);
+            index
+        } else {
+            self.entries.push(Some(entry));
+            self.listed.push(false);
+            self.entries.len() - 1
+        };
+
+        // a removed volume reinserted right away keeps its place in the sorted list
+        if !self.listed[index] {
+            self.listed[index] = true;
+            self.sorted.push(index);
+            self.appended += 1;
+        }
+        index
+    }
+
+    fn remove(&mut self, handle: Self::Handle) -> Option<D> {
+        // the index is dropped from the sorted list on the next update
+        let entry = self.entries.get_mut(handle)?.take()?;
+        self.free.push(handle);
+        Some(entry.data)
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.listed.clear();
+        self.free.clear();
+        self.sorted.clear();
+        self.appended = 0;
+    }
+
+    fn update(&mut self) {
+        let mut sorted = core::mem::take(&mut self.sorted);
+
+        // forget the volumes removed and not reinserted
+        sorted.retain(|&index| {
+            let live = self.entries[index].is_some();
+            self.listed[index] = live;
+            live
+        });
+
+        if self.appended * 2 > sorted.len() {
+            // mostly new volumes, sort them from scratch
+            sorted.sort_unstable_by(|&a, &b| self.lower(a).total_cmp(&self.lower(b)));
+        } else {
+            // the volumes moved a little since the last update, insertion sort is near linear
+            for i in 1..sorted.len() {
+                let index = sorted[i];
+                let lower = self.lower(index);
+                let mut j = i;
+                while j > 0 && self.lower(sorted[j - 1]) > lower {
+                    sorted[j] = sorted[j - 1];
+                    j -= 1;
+                }
+                sorted[j] = index;
+            }
+        }
+
+        self.sorted = sorted;
+        self.appended = 0;
+    }
+
+    fn for_each_overlaps(&self, volume: &Aabb, mut on_overlap: impl FnMut(&D)) {
+        // only the volumes starting before the end of the query can overlap it
+        let upper = volume.aabb().maxs[self.axis];
+        let end = self
+            .sorted
+            .partition_point(|&index| self.lower(index) <= upper);
+        for &index in &self.sorted[..end] {
+            if let Some(entry) = self.entry(index)
+                && entry.volume.overlaps(volume)
+            {
+                on_overlap(&entry.data);
+            }
+        }
+    }
+
+    fn for_each_overlaping_pair(&self, mut on_overlaping_pair: impl FnMut(&D, &D)) {
+        for (i, &index1) in self.sorted.iter().enumerate() {
+            let Some(entry1) = self.entry(index1) else {
+                continue;
+            };
+            let upper = self.upper(index1);
+
+            // sweep until the volumes start after the end of this one
+            for &index2 in &self.sorted[i + 1..] {
+                if self.lower(index2) > upper {
+                    break;
+                }
+                if let Some(entry2) = self.entry(index2)
+                    && entry1.volume.overlaps(&entry2.volume)
+                {
+                    on_overlaping_pair(&entry1.data, &entry2.data);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! Events reported by the world during an update
+
+#[cfg(feature = "2d")]
+use crate::object::tile_map::Cell;
+use crate::object::trigger_area::Overlap;
+use parry::query::ShapeCastHit;
+
+/// Event produced by the world during an update
+#[derive(Debug, Clone)]
+pub enum Event<T = (), B = ()> {
+    /// A kinematic body hit a static body
+    Static {
+        /// Shape cast hit data from the kinematic body point of view
+        hit: ShapeCastHit,
+
+        /// Payload of the kinematic body
+        kinematic: B,
+
+        /// Payload of the static body
+        other: B,
+    },
+
+    /// A kinematic body hit a tile of a tile map
+    #[cfg(feature = "2d")]
+    Tile {
+        /// Shape cast hit data from the kinematic body point of view
+        hit: ShapeCastHit,
+
+        /// Payload of the kinematic body
+        kinematic: B,
+
+        /// Payload of the tile map
+        other: B,
+
+        /// Cell of the tile hit
+        cell: Cell,
+    },
+
+    /// A kinematic body hit a moving platform
+    Platform {
+        /// Shape cast hit data from the kinematic body point of view
+        hit: ShapeCastHit,
+
+        /// Payload of the kinematic body
+        kinematic: B,
+
+        /// Payload of the moving platform
+        other: B,
+
+        /// Specify if the kinematic body stands on the platform
+        carried: bool,
+    },
+
+    /// Two kinematic bodies hit each other
+    Kinematic {
+        /// Shape cast hit data from the first kinematic body point of view
+        hit: ShapeCastHit,
+
+        /// Payload of the first kinematic body
+        kinematic1: B,
+
+        /// Payload of the second kinematic body
+        kinematic2: B,
+    },
+
+    /// A kinematic body overlapped with a trigger area
+    Trigger {
+        /// Stage of the overlap
+        overlap: Overlap,
+
+        /// Payload of the trigger area
+        trigger: T,
+
+        /// Payload of the kinematic body
+        kinematic: B,
+    },
+}
@@ -0,0 +1,294 @@
+//! Filter selecting the objects considered by spatial queries
+
+use super::{
+    aabb::Aabb,
+    handle::{KinematicHandle, PlatformHandle, StaticHandle, TriggerHandle},
+};
+#[cfg(feature = "2d")]
+use crate::{object::tile_map::TileMap, world::handle::TileMapHandle};
+use crate::{object::Object, Mask};
+use alloc::vec::Vec;
+use parry::bounding_volume as p;
+
+/// Filter shared by all the queries of the world.
+/// By default every object of every kind is considered.
+pub struct QueryFilter<'a, T = (), B = ()> {
+    /// Layers of the query, compared to the mask of the objects.
+    /// Trigger areas are considered if they detect one of these layers.
+    layer: Mask,
+
+    /// Layers of the objects to consider
+    mask: Mask,
+
+    /// Specify if static bodies are considered
+    statics: bool,
+
+    /// Specify if moving platforms are considered
+    platforms: bool,
+
+    /// Specify if kinematic bodies are considered
+    kinematics: bool,
+
+    /// Specify if trigger areas are considered
+    triggers: bool,
+
+    /// Specify if the tiles of the tile maps are considered
+    #[cfg(feature = "2d")]
+    tile_maps: bool,
+
+    /// Static bodies to ignore
+    excluded_statics: Vec<StaticHandle<B>>,
+
+    /// Moving platforms to ignore
+    excluded_platforms: Vec<PlatformHandle<B>>,
+
+    /// Kinematic bodies to ignore
+    excluded_kinematics: Vec<KinematicHandle<B>>,
+
+    /// Trigger areas to ignore
+    excluded_triggers: Vec<TriggerHandle<T, B>>,
+
+    /// Tile maps to ignore
+    #[cfg(feature = "2d")]
+    excluded_tile_maps: Vec<TileMapHandle<B>>,
+
+    /// Predicate over the payload of the bodies to consider
+    predicate: Option<&'a (dyn Fn(&B) -> bool + Sync)>,
+
+    /// Predicate over the payload of the trigger areas to consider
+    trigger_predicate: Option<&'a (dyn Fn(&T) -> bool + Sync)>,
+}
+
+impl<T, B> Default for QueryFilter<'_, T, B> {
+    fn default() -> Self {
+        Self {
+            layer: Mask::MAX,
+            mask: Mask::MAX,
+            statics: true,
+            platforms: true,
+            kinematics: true,
+            triggers: true,
+            #[cfg(feature = "2d")]
+            tile_maps: true,
+            excluded_statics: Vec::new(),
+            excluded_platforms: Vec::new(),
+            excluded_kinematics: Vec::new(),
+            excluded_triggers: Vec::new(),
+            #[cfg(feature = "2d")]
+            excluded_tile_maps: Vec::new(),
+            predicate: None,
+            trigger_predicate: None,
+        }
+    }
+}
+
+impl<'a, T, B> QueryFilter<'a, T, B> {
+    /// Create a filter considering every object
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the layers of the query
+    #[inline]
+    pub fn with_layer(mut self, layer: Mask) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    /// Set the layers of the objects to consider
+    #[inline]
+    pub fn with_mask(mut self, mask: Mask) -> Self {
+        self.mask = mask;
+        self
+    }
+
+    /// Specify if static bodies are considered
+    #[inline]
+    pub fn with_statics(mut self, statics: bool) -> Self {
+        self.statics = statics;
+        self
+    }
+
+    /// Specify if moving platforms are considered
+    #[inline]
+    pub fn with_platforms(mut self, platforms: bool) -> Self {
+        self.platforms = platforms;
+        self
+    }
+
+    /// Specify if kinematic bodies are considered
+    #[inline]
+    pub fn with_kinematics(mut self, kinematics: bool) -> Self {
+        self.kinematics = kinematics;
+        self
+    }
+
+    /// Specify if trigger areas are considered
+    #[inline]
+    pub fn with_triggers(mut self, triggers: bool) -> Self {
+        self.triggers = triggers;
+        self
+    }
+
+    /// Specify if the tiles of the tile maps are considered
+    #[cfg(feature = "2d")]
+    #[inline]
+    pub fn with_tile_maps(mut self, tile_maps: bool) -> Self {
+        self.tile_maps = tile_maps;
+        self
+    }
+
+    /// Ignore the given static body
+    #[inline]
+    pub fn exclude_static(mut self, handle: StaticHandle<B>) -> Self {
+        self.excluded_statics.push(handle);
+        self
+    }
+
+    /// Ignore the given moving platform
+    #[inline]
+    pub fn exclude_platform(mut self, handle: PlatformHandle<B>) -> Self {
+        self.excluded_platforms.push(handle);
+        self
+    }
+
+    /// Ignore the given kinematic body
+    #[inline]
+    pub fn exclude_kinematic(mut self, handle: KinematicHandle<B>) -> Self {
+        self.excluded_kinematics.push(handle);
+        self
+    }
+
+    /// Ignore the given trigger area
+    #[inline]
+    pub fn exclude_trigger(mut self, handle: TriggerHandle<T, B>) -> Self {
+        self.excluded_triggers.push(handle);
+        self
+    }
+
+    /// Ignore the given tile map
+    #[cfg(feature = "2d")]
+    #[inline]
+    pub fn exclude_tile_map(mut self, handle: TileMapHandle<B>) -> Self {
+        self.excluded_tile_maps.push(handle);
+        self
+    }
+
+    /// Only consider the bodies whose payload satisfies the predicate
+    #[inline]
+    pub fn with_predicate(mut self, predicate: &'a (dyn Fn(&B) -> bool + Sync)) -> Self {
+        self.predicate = Some(predicate);
+        self
+    }
+
+    /// Only consider the trigger areas whose payload satisfies the predicate
+    #[inline]
+    pub fn with_trigger_predicate(mut self, predicate: &'a (dyn Fn(&T) -> bool + Sync)) -> Self {
+        self.trigger_predicate = Some(predicate);
+        self
+    }
+
+    /// Layers of the query
+    #[inline]
+    pub fn layer(&self) -> Mask {
+        self.layer
+    }
+
+    /// Layers of the objects to consider
+    #[inline]
+    pub fn mask(&self) -> Mask {
+        self.mask
+    }
+
+    /// Check if static bodies are considered
+    #[inline]
+    pub fn statics(&self) -> bool {
+        self.statics
+    }
+
+    /// Check if moving platforms are considered
+    #[inline]
+    pub fn platforms(&self) -> bool {
+        self.platforms
+    }
+
+    /// Check if kinematic bodies are considered
+    #[inline]
+    pub fn kinematics(&self) -> bool {
+        self.kinematics
+    }
+
+    /// Check if trigger areas are considered
+    #[inline]
+    pub fn triggers(&self) -> bool {
+        self.triggers
+    }
+
+    /// Check if the tiles of the tile maps are considered
+    #[cfg(feature = "2d")]
+    #[inline]
+    pub fn tile_maps(&self) -> bool {
+        self.tile_maps
+    }
+
+    /// Static bodies ignored by the query
+    #[inline]
+    pub fn excluded_statics(&self) -> &[StaticHandle<B>] {
+        &self.excluded_statics
+    }
+
+    /// Moving platforms ignored by the query
+    #[inline]
+    pub fn excluded_platforms(&self) -> &[PlatformHandle<B>] {
+        &self.excluded_platforms
+    }
+
+    /// Kinematic bodies ignored by the query
+    #[inline]
+    pub fn excluded_kinematics(&self) -> &[KinematicHandle<B>] {
+        &self.excluded_kinematics
+    }
+
+    /// Trigger areas ignored by the query
+    #[inline]
+    pub fn excluded_triggers(&self) -> &[TriggerHandle<T, B>] {
+        &self.excluded_triggers
+    }
+
+    /// Tile maps ignored by the query
+    #[cfg(feature = "2d")]
+    #[inline]
+    pub fn excluded_tile_maps(&self) -> &[TileMapHandle<B>] {
+        &self.excluded_tile_maps
+    }
+
+    /// Wrap the bounds of the query with the layers of the filter
+    #[inline]
+    pub(crate) fn aabb(&self, aabb: p::Aabb) -> Aabb {
+        Aabb::new(aabb, self.layer, self.mask)
+    }
+
+    /// Check if a body passes the predicate of the filter
+    pub(crate) fn accepts_body<O>(&self, body: &O) -> bool
+    where
+        O: Object<Payload = B>,
+    {
+        self.predicate.is_none_or(|test| test(body.payload()))
+    }
+
+    /// Check if a tile map passes the predicate of the filter
+    #[cfg(feature = "2d")]
+    pub(crate) fn accepts_tile_map(&self, map: &TileMap<B>) -> bool {
+        self.predicate.is_none_or(|test| test(map.payload()))
+    }
+
+    /// Check if a trigger area passes the predicate of the filter
+    pub(crate) fn accepts_trigger<O>(&self, area: &O) -> bool
+    where
+        O: Object<Payload = T>,
+    {
+        self.trigger_predicate
+            .is_none_or(|test| test(area.payload()))
+    }
+}
@@ -0,0 +1,115 @@
+//! Generational handles referring to the objects stored in a world
+
+use crate::object::{
+    kinematic_body::KinematicBody, moving_platform::MovingPlatform, static_body::StaticBody,
+    trigger_area::TriggerArea,
+};
+use core::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+
+/// Handle to an object stored in a set.
+/// A handle outlived by its object is detected through its generation.
+pub struct Handle<O> {
+    /// Index of the slot holding the object
+    index: u32,
+
+    /// Generation of the slot when the object was stored
+    generation: u32,
+
+    /// Type of the object referred to
+    marker: PhantomData<fn() -> O>,
+}
+
+/// Handle to a kinematic body
+pub type KinematicHandle<B = ()> = Handle<KinematicBody<B>>;
+
+/// Handle to a static body
+pub type StaticHandle<B = ()> = Handle<StaticBody<B>>;
+
+/// Handle to a moving platform
+pub type PlatformHandle<B = ()> = Handle<MovingPlatform<B>>;
+
+/// Handle to a trigger area
+pub type TriggerHandle<T = (), B = ()> = Handle<TriggerArea<T, B>>;
+
+/// Handle to a tile map
+#[cfg(feature = "2d")]
+pub type TileMapHandle<B = ()> = Handle<crate::object::tile_map::TileMap<B>>;
+
+impl<O> Handle<O> {
+    /// Create a new handle
+    #[inline]
+    pub(crate) fn new(index: u32, generation: u32) -> Self {
+        Self {
+            index,
+            generation,
+            marker: PhantomData,
+        }
+    }
+
+    /// Index of the slot holding the object
+    #[inline]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Generation of the slot when the object was stored
+    #[inline]
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+// implement the traits by hand to avoid requiring them on the object type
+
+impl<O> Clone for Handle<O> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<O> Copy for Handle<O> {}
+
+impl<O> PartialEq for Handle<O> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<O> Eq for Handle<O> {}
+
+impl<O> PartialOrd for Handle<O> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<O> Ord for Handle<O> {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.index, self.generation).cmp(&(other.index, other.generation))
+    }
+}
+
+impl<O> Hash for Handle<O> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<O> fmt::Debug for Handle<O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handle")
+            .field("index", &self.index)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
@@ -0,0 +1,753 @@
+//! Spatial queries against the objects of the world
+
+use super::{broadphase::Broadphase, filter::QueryFilter, handle::Handle, set::Set, World};
+#[cfg(feature = "2d")]
+use crate::object::tile_map::{Cell, TileMap};
+use crate::{
+    object::{
+        cast_shapes, kinematic_body::KinematicBody, moving_platform::MovingPlatform,
+        static_body::StaticBody, trigger_area::TriggerArea, Object,
+    },
+    world::aabb::Aabb,
+    Shared,
+};
+use alloc::{sync::Arc, vec::Vec};
+#[cfg(feature = "2d")]
+use bvh_arena::BoundingVolume as _;
+use parry::{
+    bounding_volume::{self as p, BoundingVolume},
+    math::{Isometry, Point, Real, Vector},
+    query::{self, ClosestPoints, Ray, RayIntersection, ShapeCastHit, ShapeCastOptions},
+    shape::Shape,
+};
+
+impl<B, T, P: Broadphase> World<T, B, P> {
+    /// Perform a raycast and find the nearest solid object along the ray
+    pub fn raycast(
+        &self,
+        ray: &Ray,
+        max_time_of_impact: Real,
+        filter: &QueryFilter<T, B>,
+    ) -> RayResult<B> {
+        let probe = RayProbe {
+            ray,
+            max_time_of_impact,
+            #[cfg(feature = "2d")]
+            all: false,
+        };
+
+        // Try to find the best candidate
+        let mut found = RayResult::None;
+        let mut time = Real::MAX;
+        self.probe(&probe, filter, false, |hit, object| {
+            // if the hit is closer to the origin, replace the previous result
+            if hit.time_of_impact < time {
+                time = hit.time_of_impact;
+                found = RayResult::new(hit, object);
+            }
+        });
+        found
+    }
+
+    /// Perform a raycast and find every object along the ray.
+    /// The hits are sorted by distance and the closest ones are kept up to the limit.
+    pub fn raycast_all(
+        &self,
+        ray: &Ray,
+        max_time_of_impact: Real,
+        filter: &QueryFilter<T, B>,
+        limit: Option<usize>,
+    ) -> Vec<RayHit<T, B>> {
+        let probe = RayProbe {
+            ray,
+            max_time_of_impact,
+            #[cfg(feature = "2d")]
+            all: true,
+        };
+
+        let mut found = Vec::new();
+        self.probe(&probe, filter, true, |hit, object| {
+            found.push(RayHit { hit, object })
+        });
+
+        // Keep the closest hits first
+        found.sort_by(|a, b| a.hit.time_of_impact.total_cmp(&b.hit.time_of_impact));
+        if let Some(limit) = limit {
+            found.truncate(limit);
+        }
+        found
+    }
+
+    /// Cast a shape through the world and find the nearest solid object it hits.
+    /// Objects are considered still at their current isometry.
+    pub fn shapecast(
+        &self,
+        shape: &dyn Shape,
+        isometry: &Isometry<Real>,
+        velocity: &Vector<Real>,
+        max_time_of_impact: Real,
+        filter: &QueryFilter<T, B>,
+    ) -> ShapeCastResult<B> {
+        let probe = CastProbe::new(shape, isometry, velocity, max_time_of_impact);
+
+        let mut found = ShapeCastResult::None;
+        let mut time = Real::MAX;
+        self.probe(&probe, filter, false, |hit, object| {
+            // if the hit is closer to the origin, replace the previous result
+            if hit.time_of_impact < time {
+                time = hit.time_of_impact;
+                found = ShapeCastResult::new(hit, object);
+            }
+        });
+        found
+    }
+
+    /// Cast a shape through the world and find every solid object it hits.
+    /// The results are sorted by time of impact.
+    pub fn shapecast_all(
+        &self,
+        shape: &dyn Shape,
+        isometry: &Isometry<Real>,
+        velocity: &Vector<Real>,
+        max_time_of_impact: Real,
+        filter: &QueryFilter<T, B>,
+    ) -> Vec<ShapeHit<T, B>> {
+        let probe = CastProbe::new(shape, isometry, velocity, max_time_of_impact);
+
+        let mut found = Vec::new();
+        self.probe(&probe, filter, false, |hit, object| {
+            found.push(ShapeHit { hit, object })
+        });
+
+        found.sort_by(|a, b| a.hit.time_of_impact.total_cmp(&b.hit.time_of_impact));
+        found
+    }
+
+    /// Find every object containing the given point.
+    /// Use the filter to only look for trigger areas.
+    pub fn point_query(
+        &self,
+        point: &Point<Real>,
+        filter: &QueryFilter<T, B>,
+    ) -> Vec<ObjectRef<T, B>> {
+        let mut found = Vec::new();
+        self.probe(&PointProbe { point }, filter, true, |_, object| {
+            found.push(object)
+        });
+        found
+    }
+
+    /// Find every object whose shape intersects the given shape
+    pub fn intersect_shape(
+        &self,
+        shape: &dyn Shape,
+        isometry: &Isometry<Real>,
+        filter: &QueryFilter<T, B>,
+    ) -> Vec<ObjectRef<T, B>> {
+        let mut found = Vec::new();
+        self.probe(
+            &ShapeProbe { shape, isometry },
+            filter,
+            true,
+            |_, object| found.push(object),
+        );
+        found
+    }
+
+    /// Wake up every sleeping kinematic body whose shape intersects the given shape.
+    /// Return the number of bodies woken up.
+    pub fn wake_area(
+        &self,
+        shape: &dyn Shape,
+        isometry: &Isometry<Real>,
+        filter: &QueryFilter<T, B>,
+    ) -> usize {
+        let mut count = 0;
+        for object in self.intersect_shape(shape, isometry, filter) {
+            if let ObjectRef::Kinematic { object } = object {
+                let mut body = object.write();
+                if body.is_sleeping() {
+                    body.wake_up();
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// Find the nearest object within the maximum distance of a point or a shape
+    pub fn closest(
+        &self,
+        target: Target,
+        max_distance: Real,
+        filter: &QueryFilter<T, B>,
+    ) -> Option<Closest<T, B>> {
+        let probe = ClosestProbe {
+            target,
+            max_distance,
+        };
+
+        let mut found: Option<Closest<T, B>> = None;
+        self.probe(
+            &probe,
+            filter,
+            true,
+            |(distance, point1, point2), object| {
+                // if the object is closer, replace the previous result
+                if found.as_ref().is_none_or(|best| distance < best.distance) {
+                    found = Some(Closest {
+                        distance,
+                        point1,
+                        point2,
+                        object,
+                    });
+                }
+            },
+        );
+        found
+    }
+
+    /// Run a probe against every object passing the filter
+    fn probe<Q: Probe>(
+        &self,
+        probe: &Q,
+        filter: &QueryFilter<T, B>,
+        triggers: bool,
+        mut on_hit: impl FnMut(Q::Hit, ObjectRef<T, B>),
+    ) {
+        let aabb = filter.aabb(probe.bounds());
+
+        // Check trigger areas
+        if triggers && filter.triggers() {
+            probe_set(
+                &self.trigger_set,
+                &aabb,
+                probe,
+                filter.excluded_triggers(),
+                |area| filter.accepts_trigger(area),
+                |hit, object| on_hit(hit, ObjectRef::Trigger { object }),
+            );
+        }
+
+        // Check static bodies
+        if filter.statics() {
+            probe_set(
+                &self.static_set,
+                &aabb,
+                probe,
+                filter.excluded_statics(),
+                |body| filter.accepts_body(body),
+                |hit, object| on_hit(hit, ObjectRef::Static { object }),
+            );
+        }
+
+        // Check the tiles of the tile maps
+        #[cfg(feature = "2d")]
+        if filter.tile_maps() {
+            for (handle, object) in self.tile_set.entries() {
+                if filter.excluded_tile_maps().contains(&handle) {
+                    continue;
+                }
+                let map = object.read();
+                if aabb.overlaps(&map.aabb()) && filter.accepts_tile_map(&map) {
+                    probe.test_tiles(&map, |cell, hit| {
+                        let object = object.clone();
+                        on_hit(hit, ObjectRef::TileMap { object, cell })
+                    });
+                }
+            }
+        }
+
+        // Check moving platforms
+        if filter.platforms() {
+            probe_set(
+                &self.platform_set,
+                &aabb,
+                probe,
+                filter.excluded_platforms(),
+                |body| filter.accepts_body(body),
+                |hit, object| on_hit(hit, ObjectRef::Platform { object }),
+            );
+        }
+
+        // Check kinematic bodies
+        if filter.kinematics() {
+            probe_set(
+                &self.kinematic_set,
+                &aabb,
+                probe,
+                filter.excluded_kinematics(),
+                |body| filter.accepts_body(body),
+                |hit, object| on_hit(hit, ObjectRef::Kinematic { object }),
+            );
+        }
+    }
+}
+
+/// Run a probe against every object of a set overlapping the given AABB.
+/// The excluded handles are looked up in the set, those of removed objects match nothing.
+fn probe_set<O: Object, P: Broadphase, Q: Probe>(
+    set: &Set<O, P>,
+    aabb: &Aabb,
+    probe: &Q,
+    excluded: &[Handle<O>],
+    accepts: impl Fn(&O) -> bool,
+    mut on_hit: impl FnMut(Q::Hit, Shared<O>),
+) {
+    set.query(aabb, |object| {
+        let ignored = excluded.iter().any(|handle| {
+            set.get(*handle)
+                .is_some_and(|other| Arc::ptr_eq(other, object))
+        });
+        if ignored {
+            return;
+        }
+        let o = object.read();
+        if accepts(&o)
+            && let Some(hit) = probe.test(o.shape(), o.isometry())
+        {
+            on_hit(hit, object.clone());
+        }
+    });
+}
+
+/// Geometric test run against the objects of the world
+trait Probe {
+    /// Data produced when an object passes the test
+    type Hit;
+
+    /// Bounds of the region covered by the probe
+    fn bounds(&self) -> p::Aabb;
+
+    /// Test the probe against the shape of an object
+    fn test(&self, shape: &dyn Shape, isometry: &Isometry<Real>) -> Option<Self::Hit>;
+
+    /// Test the probe against the tiles of a map overlapping its bounds
+    #[cfg(feature = "2d")]
+    fn test_tiles<B>(&self, map: &TileMap<B>, mut on_hit: impl FnMut(Cell, Self::Hit)) {
+        map.for_each_tile(&self.bounds(), |cell, shape, isometry| {
+            if let Some(hit) = self.test(shape.as_ref(), isometry) {
+                on_hit(cell, hit);
+            }
+        });
+    }
+}
+
+/// Ray hitting the objects it crosses
+struct RayProbe<'a> {
+    /// Ray to cast
+    ray: &'a Ray,
+
+    /// Maximum time of impact along the ray
+    max_time_of_impact: Real,
+
+    /// Specify if every hit is wanted, or only the nearest one
+    #[cfg(feature = "2d")]
+    all: bool,
+}
+
+impl Probe for RayProbe<'_> {
+    type Hit = RayIntersection;
+
+    fn bounds(&self) -> p::Aabb {
+        let (mins, maxs) = self
+            .ray
+            .origin
+            .coords
+            .inf_sup(&self.ray.point_at(self.max_time_of_impact).coords);
+        p::Aabb::new(Point::from(mins), Point::from(maxs))
+    }
+
+    #[inline]
+    fn test(&self, shape: &dyn Shape, isometry: &Isometry<Real>) -> Option<Self::Hit> {
+        shape.cast_ray_and_get_normal(isometry, self.ray, self.max_time_of_impact, true)
+    }
+
+    /// Walk the cells along the ray instead of testing every cell of its bounds
+    #[cfg(feature = "2d")]
+    fn test_tiles<B>(&self, map: &TileMap<B>, mut on_hit: impl FnMut(Cell, Self::Hit)) {
+        map.cast_ray(self.ray, self.max_time_of_impact, true, |cell, hit| {
+            on_hit(cell, hit);
+            // the cells are crossed in order, the first hit is the nearest
+            self.all
+        });
+    }
+}
+
+/// Moving shape hitting the still objects on its way
+struct CastProbe<'a> {
+    /// Shape to cast
+    shape: &'a dyn Shape,
+
+    /// Starting isometry of the shape
+    isometry: &'a Isometry<Real>,
+
+    /// Velocity of the shape
+    velocity: &'a Vector<Real>,
+
+    /// Options of the shape cast
+    options: ShapeCastOptions,
+}
+
+impl<'a> CastProbe<'a> {
+    /// Create a new shape cast probe
+    #[inline]
+    fn new(
+        shape: &'a dyn Shape,
+        isometry: &'a Isometry<Real>,
+        velocity: &'a Vector<Real>,
+        max_time_of_impact: Real,
+    ) -> Self {
+        Self {
+            shape,
+            isometry,
+            velocity,
+            options: ShapeCastOptions::with_max_time_of_impact(max_time_of_impact),
+        }
+    }
+}
+
+impl Probe for CastProbe<'_> {
+    type Hit = ShapeCastHit;
+
+    fn bounds(&self) -> p::Aabb {
+        let mut target = *self.isometry;
+        target.translation.vector += self.velocity * self.options.max_time_of_impact;
+        self.shape.compute_swept_aabb(self.isometry, &target)
+    }
+
+    #[inline]
+    fn test(&self, shape: &dyn Shape, isometry: &Isometry<Real>) -> Option<Self::Hit> {
+        cast_shapes(
+            self.isometry,
+            self.velocity,
+            self.shape,
+            isometry,
+            &Vector::zeros(),
+            shape,
+            self.options,
+        )
+    }
+}
+
+/// Point contained by the objects
+struct PointProbe<'a> {
+    /// Point to look for
+    point: &'a Point<Real>,
+}
+
+impl Probe for PointProbe<'_> {
+    type Hit = ();
+
+    #[inline]
+    fn bounds(&self) -> p::Aabb {
+        p::Aabb::new(*self.point, *self.point)
+    }
+
+    #[inline]
+    fn test(&self, shape: &dyn Shape, isometry: &Isometry<Real>) -> Option<Self::Hit> {
+        shape.contains_point(isometry, self.point).then_some(())
+    }
+}
+
+/// Shape intersecting the objects
+struct ShapeProbe<'a> {
+    /// Shape to test
+    shape: &'a dyn Shape,
+
+    /// Isometry of the shape
+    isometry: &'a Isometry<Real>,
+}
+
+impl Probe for ShapeProbe<'_> {
+    type Hit = ();
+
+    #[inline]
+    fn bounds(&self) -> p::Aabb {
+        self.shape.compute_aabb(self.isometry)
+    }
+
+    #[inline]
+    fn test(&self, shape: &dyn Shape, isometry: &Isometry<Real>) -> Option<Self::Hit> {
+        query::intersection_test(self.isometry, self.shape, isometry, shape)
+            .unwrap_or(false)
+            .then_some(())
+    }
+}
+
+/// Point or shape to measure the distance from
+#[derive(Clone, Copy)]
+pub enum Target<'a> {
+    /// A single point
+    Point(&'a Point<Real>),
+
+    /// A shape at the given isometry
+    Shape(&'a dyn Shape, &'a Isometry<Real>),
+}
+
+/// Closest points between the target and the objects
+struct ClosestProbe<'a> {
+    /// Point or shape to measure the distance from
+    target: Target<'a>,
+
+    /// Maximum distance at which objects are considered
+    max_distance: Real,
+}
+
+impl Probe for ClosestProbe<'_> {
+    type Hit = (Real, Point<Real>, Point<Real>);
+
+    fn bounds(&self) -> p::Aabb {
+        match self.target {
+            Target::Point(point) => p::Aabb::new(*point, *point),
+            Target::Shape(shape, isometry) => shape.compute_aabb(isometry),
+        }
+        .loosened(self.max_distance)
+    }
+
+    fn test(&self, shape: &dyn Shape, isometry: &Isometry<Real>) -> Option<Self::Hit> {
+        match self.target {
+            Target::Point(point) => {
+                let projection = shape.project_point(isometry, point, true);
+                let distance = if projection.is_inside {
+                    0.0
+                } else {
+                    (projection.point - point).norm()
+                };
+                (distance <= self.max_distance).then_some((distance, *point, projection.point))
+            }
+            Target::Shape(target, target_isometry) => {
+                match query::closest_points(
+                    target_isometry,
+                    target,
+                    isometry,
+                    shape,
+                    self.max_distance,
+                )
+                .ok()?
+                {
+                    ClosestPoints::WithinMargin(point1, point2) => {
+                        Some(((point2 - point1).norm(), point1, point2))
+                    }
+                    // report the penetration depth as a negative distance
+                    ClosestPoints::Intersecting => {
+                        query::contact(target_isometry, target, isometry, shape, 0.0)
+                            .ok()
+                            .flatten()
+                            .map(|contact| (contact.dist, contact.point1, contact.point2))
+                    }
+                    ClosestPoints::Disjoint => None,
+                }
+            }
+        }
+    }
+}
+
+/// Return data relative to the object that have been hit by the raycast
+pub enum RayResult<P> {
+    /// No object has been hit
+    None,
+
+    /// The object hit is a static body
+    Static {
+        /// Ray intersection data
+        hit: RayIntersection,
+
+        /// Reference to the object
+        object: Shared<StaticBody<P>>,
+    },
+
+    /// The object hit is a tile of a tile map
+    #[cfg(feature = "2d")]
+    TileMap {
+        /// Ray intersection data
+        hit: RayIntersection,
+
+        /// Reference to the object
+        object: Shared<TileMap<P>>,
+
+        /// Cell of the tile hit
+        cell: Cell,
+    },
+
+    /// The object hit is a moving platform
+    Platform {
+        /// Ray intersection data
+        hit: RayIntersection,
+
+        /// Reference to the object
+        object: Shared<MovingPlatform<P>>,
+    },
+
+    /// The object hit is a kinematic body
+    Kinematic {
+        /// Ray intersection data
+        hit: RayIntersection,
+
+        /// Reference to the object
+        object: Shared<KinematicBody<P>>,
+    },
+}
+
+impl<P> RayResult<P> {
+    /// Build the result for a solid object hit by the ray
+    fn new<T>(hit: RayIntersection, object: ObjectRef<T, P>) -> Self {
+        match object {
+            ObjectRef::Trigger { .. } => Self::None,
+            ObjectRef::Static { object } => Self::Static { hit, object },
+            #[cfg(feature = "2d")]
+            ObjectRef::TileMap { object, cell } => Self::TileMap { hit, object, cell },
+            ObjectRef::Platform { object } => Self::Platform { hit, object },
+            ObjectRef::Kinematic { object } => Self::Kinematic { hit, object },
+        }
+    }
+}
+
+/// Object hit along a ray
+pub struct RayHit<T, B> {
+    /// Ray intersection data
+    pub hit: RayIntersection,
+
+    /// Reference to the object
+    pub object: ObjectRef<T, B>,
+}
+
+/// Return data relative to the object that have been hit by the shapecast
+pub enum ShapeCastResult<P> {
+    /// No object has been hit
+    None,
+
+    /// The object hit is a static body
+    Static {
+        /// Shape cast hit data from the cast shape point of view
+        hit: ShapeCastHit,
+
+        /// Reference to the object
+        object: Shared<StaticBody<P>>,
+    },
+
+    /// The object hit is a tile of a tile map
+    #[cfg(feature = "2d")]
+    TileMap {
+        /// Shape cast hit data from the cast shape point of view
+        hit: ShapeCastHit,
+
+        /// Reference to the object
+        object: Shared<TileMap<P>>,
+
+        /// Cell of the tile hit
+        cell: Cell,
+    },
+
+    /// The object hit is a moving platform
+    Platform {
+        /// Shape cast hit data from the cast shape point of view
+        hit: ShapeCastHit,
+
+        /// Reference to the object
+        object: Shared<MovingPlatform<P>>,
+    },
+
+    /// The object hit is a kinematic body
+    Kinematic {
+        /// Shape cast hit data from the cast shape point of view
+        hit: ShapeCastHit,
+
+        /// Reference to the object
+        object: Shared<KinematicBody<P>>,
+    },
+}
+
+impl<P> ShapeCastResult<P> {
+    /// Build the result for a solid object hit by the shape
+    fn new<T>(hit: ShapeCastHit, object: ObjectRef<T, P>) -> Self {
+        match object {
+            ObjectRef::Trigger { .. } => Self::None,
+            ObjectRef::Static { object } => Self::Static { hit, object },
+            #[cfg(feature = "2d")]
+            ObjectRef::TileMap { object, cell } => Self::TileMap { hit, object, cell },
+            ObjectRef::Platform { object } => Self::Platform { hit, object },
+            ObjectRef::Kinematic { object } => Self::Kinematic { hit, object },
+        }
+    }
+
+    /// Access the hit data if an object has been hit
+    #[inline]
+    pub fn hit(&self) -> Option<&ShapeCastHit> {
+        match self {
+            Self::None => None,
+            Self::Static { hit, .. } | Self::Platform { hit, .. } | Self::Kinematic { hit, .. } => {
+                Some(hit)
+            }
+            #[cfg(feature = "2d")]
+            Self::TileMap { hit, .. } => Some(hit),
+        }
+    }
+
+    /// Time of impact of the hit, infinite if no object has been hit
+    #[inline]
+    pub fn time_of_impact(&self) -> Real {
+        self.hit().map_or(Real::INFINITY, |hit| hit.time_of_impact)
+    }
+}
+
+/// Object hit by a shape cast
+pub struct ShapeHit<T, B> {
+    /// Shape cast hit data from the cast shape point of view
+    pub hit: ShapeCastHit,
+
+    /// Reference to the object
+    pub object: ObjectRef<T, B>,
+}
+
+/// Typed reference to an object found by a query
+pub enum ObjectRef<T, B> {
+    /// The object is a trigger area
+    Trigger {
+        /// Reference to the object
+        object: Shared<TriggerArea<T, B>>,
+    },
+
+    /// The object is a static body
+    Static {
+        /// Reference to the object
+        object: Shared<StaticBody<B>>,
+    },
+
+    /// The object is a tile of a tile map
+    #[cfg(feature = "2d")]
+    TileMap {
+        /// Reference to the object
+        object: Shared<TileMap<B>>,
+
+        /// Cell of the tile
+        cell: Cell,
+    },
+
+    /// The object is a moving platform
+    Platform {
+        /// Reference to the object
+        object: Shared<MovingPlatform<B>>,
+    },
+
+    /// The object is a kinematic body
+    Kinematic {
+        /// Reference to the object
+        object: Shared<KinematicBody<B>>,
+    },
+}
+
+/// Nearest object found by a distance query
+pub struct Closest<T, B> {
+    /// Distance between the target and the object, negative if they penetrate
+    pub distance: Real,
+
+    /// Closest point on the target
+    pub point1: Point<Real>,
+
+    /// Closest point on the object
+    pub point2: Point<Real>,
+
+    /// Reference to the object
+    pub object: ObjectRef<T, B>,
+}
@@ -0,0 +1,468 @@
+//! Save and load the state of a world.
+//! The partitions are rebuilt on load and the recorded events are not saved.
+
+#[cfg(feature = "2d")]
+use crate::object::tile_map::TileMap;
+use crate::{
+    make_shared,
+    object::{
+        kinematic_body::KinematicBody,
+        moving_platform::MovingPlatform,
+        shape::{SavedShape, ShapeData},
+        static_body::StaticBody,
+        trigger_area::{OnOverlap, TriggerArea},
+        Object,
+    },
+    world::{broadphase::Broadphase, handle::Handle, set::Set, step::FixedStep, World},
+    Mask, Shared,
+};
+use alloc::{collections::BTreeMap, format, string::String, sync::Arc, vec::Vec};
+use parry::{
+    math::{Isometry, Real},
+    shape::Shape,
+};
+use serde::{
+    de,
+    ser::{self, SerializeSeq},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// Registry of the functions called by the trigger areas.
+/// Functions are not serializable, the trigger areas save the name they were given from the registry.
+pub struct Callbacks<T = (), B = ()> {
+    /// Registered functions along with their names
+    entries: Vec<(String, OnOverlap<T, B>)>,
+}
+
+impl<T, B> Default for Callbacks<T, B> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<T, B> Callbacks<T, B> {
+    /// Create an empty registry
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a function under the given name.
+    /// The function previously registered under this name is replaced.
+    pub fn register(&mut self, name: impl Into<String>, callback: OnOverlap<T, B>) {
+        let name = name.into();
+        match self.entries.iter_mut().find(|(entry, _)| *entry == name) {
+            Some(entry) => entry.1 = callback,
+            None => self.entries.push((name, callback)),
+        }
+    }
+
+    /// Register a function under the given name
+    #[inline]
+    pub fn with_callback(mut self, name: impl Into<String>, callback: OnOverlap<T, B>) -> Self {
+        self.register(name, callback);
+        self
+    }
+
+    /// Find the function registered under the given name
+    pub fn get(&self, name: &str) -> Option<OnOverlap<T, B>> {
+        self.entries
+            .iter()
+            .find(|(entry, _)| entry == name)
+            .map(|(_, callback)| *callback)
+    }
+}
+
+impl<B, T, P: Broadphase> World<T, B, P> {
+    /// Save the objects and the settings of the world.
+    /// The functions of the trigger areas must be set from a registry.
+    pub fn save<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: Serialize,
+        B: Serialize,
+    {
+        // each shared shape is saved once, the objects refer to it by index
+        let mut shapes = ShapeTable::default();
+        shapes.collect(&self.kinematic_set);
+        shapes.collect(&self.static_set);
+        shapes.collect(&self.platform_set);
+        shapes.collect(&self.trigger_set);
+
+        WorldRef {
+            epsilon: self.epsilon,
+            iterations: self.iterations,
+            margin: self.margin,
+            sleep_threshold: self.sleep_threshold,
+            sleep_ticks: self.sleep_ticks,
+            fixed_step: &self.fixed_step,
+            record_events: self.record_events,
+            shapes: &shapes,
+            kinematics: ObjectsRef {
+                set: &self.kinematic_set,
+                shapes: &shapes,
+            },
+            statics: ObjectsRef {
+                set: &self.static_set,
+                shapes: &shapes,
+            },
+            platforms: ObjectsRef {
+                set: &self.platform_set,
+                shapes: &shapes,
+            },
+            triggers: TriggersRef {
+                set: &self.trigger_set,
+                kinematics: &self.kinematic_set,
+                shapes: &shapes,
+            },
+            #[cfg(feature = "2d")]
+            tile_maps: TileMapsRef(&self.tile_set),
+        }
+        .serialize(serializer)
+    }
+
+    /// Load a world saved with `save`.
+    /// The functions of the trigger areas are looked up in the registry.
+    pub fn load<'de, D>(deserializer: D, callbacks: &Callbacks<T, B>) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de>,
+        B: Deserialize<'de>,
+    {
+        let data = WorldData::<T, B>::deserialize(deserializer)?;
+        let shapes = data
+            .shapes
+            .into_iter()
+            .map(|shape| {
+                shape
+                    .into_shape()
+                    .ok_or_else(|| de::Error::custom("invalid compound shape"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let kinematics = restore_slots(data.kinematics, &shapes)?;
+
+        let mut triggers = Vec::with_capacity(data.triggers.len());
+        for slot in data.triggers {
+            let area = match slot.object {
+                Some(trigger) => Some(make_shared(trigger.restore(
+                    callbacks,
+                    &shapes,
+                    &kinematics,
+                )?)),
+                None => None,
+            };
+            triggers.push((slot.generation, area));
+        }
+
+        let mut world = Self {
+            kinematic_set: Set::from_slots(kinematics),
+            static_set: Set::from_slots(restore_slots(data.statics, &shapes)?),
+            platform_set: Set::from_slots(restore_slots(data.platforms, &shapes)?),
+            trigger_set: Set::from_slots(triggers),
+            #[cfg(feature = "2d")]
+            tile_set: Set::from_slots(
+                data.tile_maps
+                    .into_iter()
+                    .map(|slot| (slot.generation, slot.object.map(make_shared))),
+            ),
+            epsilon: data.epsilon,
+            iterations: data.iterations,
+            margin: data.margin,
+            sleep_threshold: data.sleep_threshold,
+            sleep_ticks: data.sleep_ticks,
+            fixed_step: data.fixed_step,
+            record_events: data.record_events,
+            events: Vec::new(),
+        };
+        world.kinematic_set.repartition();
+        world.static_set.repartition();
+        world.platform_set.repartition();
+        world.trigger_set.repartition();
+        Ok(world)
+    }
+}
+
+/// Shapes of the objects of a world, each shared shape is listed once
+#[derive(Default)]
+struct ShapeTable {
+    /// Shapes in the order they are saved
+    shapes: Vec<Arc<dyn Shape>>,
+
+    /// Index of each shape from its address
+    indices: BTreeMap<usize, u32>,
+}
+
+impl ShapeTable {
+    /// List the shapes of the objects of a set
+    fn collect<O: Object, P: Broadphase>(&mut self, set: &Set<O, P>) {
+        for object in set.iter() {
+            let shape = object.read().shared_shape().clone();
+            self.indices
+                .entry(Arc::as_ptr(&shape).addr())
+                .or_insert_with(|| {
+                    self.shapes.push(shape);
+                    self.shapes.len() as u32 - 1
+                });
+        }
+    }
+
+    /// Index of a listed shape
+    #[inline]
+    fn index_of(&self, shape: &Arc<dyn Shape>) -> u32 {
+        self.indices[&Arc::as_ptr(shape).addr()]
+    }
+}
+
+impl Serialize for ShapeTable {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.shapes.len()))?;
+        for shape in self.shapes.iter() {
+            let data = ShapeData::from_shape(shape.as_ref())
+                .ok_or_else(|| ser::Error::custom("unsupported shape"))?;
+            seq.serialize_element(&data)?;
+        }
+        seq.end()
+    }
+}
+
+/// Look up a shape of the table
+fn shape_at<E: de::Error>(shapes: &[Arc<dyn Shape>], index: u32) -> Result<Arc<dyn Shape>, E> {
+    shapes
+        .get(index as usize)
+        .cloned()
+        .ok_or_else(|| E::custom(format!("invalid shape index: {index}")))
+}
+
+/// Slot of a set as it is saved
+#[derive(Serialize, Deserialize)]
+struct SavedSlot<O> {
+    /// Generation of the slot
+    generation: u32,
+
+    /// Object stored in the slot
+    object: Option<O>,
+}
+
+/// Object as it is saved, along with the index of its shape
+#[derive(Serialize, Deserialize)]
+struct SavedObject<O> {
+    /// Index of the shape in the table of the world
+    shape: u32,
+
+    /// Object without its shape
+    object: O,
+}
+
+/// Share the objects of saved slots along with their shape
+#[allow(clippy::type_complexity)]
+fn restore_slots<O: SavedShape, E: de::Error>(
+    slots: Vec<SavedSlot<SavedObject<O>>>,
+    shapes: &[Arc<dyn Shape>],
+) -> Result<Vec<(u32, Option<Shared<O>>)>, E> {
+    slots
+        .into_iter()
+        .map(|slot| {
+            let object = match slot.object {
+                Some(SavedObject { shape, mut object }) => {
+                    object.restore_shape(shape_at(shapes, shape)?);
+                    Some(make_shared(object))
+                }
+                None => None,
+            };
+            Ok((slot.generation, object))
+        })
+        .collect()
+}
+
+/// Save the slots of a set along with the indices of the shapes
+struct ObjectsRef<'a, O, P: Broadphase> {
+    /// Set of objects
+    set: &'a Set<O, P>,
+
+    /// Shapes of the world
+    shapes: &'a ShapeTable,
+}
+
+impl<O: Object + Serialize, P: Broadphase> Serialize for ObjectsRef<'_, O, P> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let slots = self.set.slots();
+        let mut seq = serializer.serialize_seq(Some(slots.len()))?;
+        for (generation, object) in slots {
+            let object = object.map(|object| object.read());
+            seq.serialize_element(&SavedSlot {
+                generation,
+                object: object.as_deref().map(|object| SavedObject {
+                    shape: self.shapes.index_of(object.shared_shape()),
+                    object,
+                }),
+            })?;
+        }
+        seq.end()
+    }
+}
+
+/// Save the slots of a set of tile maps, their shapes are rebuilt from their palette
+#[cfg(feature = "2d")]
+struct TileMapsRef<'a, B, P: Broadphase>(&'a Set<TileMap<B>, P>);
+
+#[cfg(feature = "2d")]
+impl<B: Serialize, P: Broadphase> Serialize for TileMapsRef<'_, B, P> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let slots = self.0.slots();
+        let mut seq = serializer.serialize_seq(Some(slots.len()))?;
+        for (generation, object) in slots {
+            let object = object.map(|object| object.read());
+            seq.serialize_element(&SavedSlot {
+                generation,
+                object: object.as_deref(),
+            })?;
+        }
+        seq.end()
+    }
+}
+
+/// Save the slots of a set of trigger areas
+struct TriggersRef<'a, T, B, P: Broadphase> {
+    /// Set of trigger areas
+    set: &'a Set<TriggerArea<T, B>, P>,
+
+    /// Set of kinematic bodies occupying the trigger areas
+    kinematics: &'a Set<KinematicBody<B>, P>,
+
+    /// Shapes of the world
+    shapes: &'a ShapeTable,
+}
+
+impl<T: Serialize, B, P: Broadphase> Serialize for TriggersRef<'_, T, B, P> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let slots = self.set.slots();
+        let mut seq = serializer.serialize_seq(Some(slots.len()))?;
+        for (generation, object) in slots {
+            let area = object.map(|object| object.read());
+            let object = match area.as_deref() {
+                Some(area) => Some(TriggerRef {
+                    shape: self.shapes.index_of(area.shared_shape()),
+                    isometry: area.isometry(),
+                    payload: area.payload(),
+                    mask: area.mask(),
+                    callback: area
+                        .callback_name()
+                        .ok_or_else(|| ser::Error::custom("trigger callback set without a name"))?,
+                    occupants: area
+                        .occupant_handles()
+                        // the bodies removed since the last update are not inside anymore
+                        .filter(|handle| self.kinematics.contains(*handle))
+                        .map(|handle| handle.index())
+                        .collect(),
+                    wake_requested: area.is_waking_occupants(),
+                }),
+                None => None,
+            };
+            seq.serialize_element(&SavedSlot { generation, object })?;
+        }
+        seq.end()
+    }
+}
+
+/// Trigger area as it is saved
+#[derive(Serialize)]
+#[serde(rename = "TriggerArea")]
+struct TriggerRef<'a, T> {
+    shape: u32,
+    isometry: &'a Isometry<Real>,
+    payload: &'a T,
+    mask: Mask,
+    callback: &'a str,
+    occupants: Vec<u32>,
+    wake_requested: bool,
+}
+
+/// Trigger area as it is loaded
+#[derive(Deserialize)]
+#[serde(rename = "TriggerArea")]
+struct TriggerData<T> {
+    shape: u32,
+    isometry: Isometry<Real>,
+    payload: T,
+    mask: Mask,
+    callback: String,
+    occupants: Vec<u32>,
+    wake_requested: bool,
+}
+
+impl<T> TriggerData<T> {
+    /// Rebuild the trigger area along with its occupants
+    fn restore<B, E: de::Error>(
+        self,
+        callbacks: &Callbacks<T, B>,
+        shapes: &[Arc<dyn Shape>],
+        kinematics: &[(u32, Option<Shared<KinematicBody<B>>>)],
+    ) -> Result<TriggerArea<T, B>, E> {
+        let occupants = self
+            .occupants
+            .iter()
+            .map(|index| match kinematics.get(*index as usize) {
+                Some((generation, Some(body))) => {
+                    Ok((Handle::new(*index, *generation), body.clone()))
+                }
+                _ => Err(E::custom(format!("invalid trigger occupant: {index}"))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut area = TriggerArea::with_callback(
+            shape_at(shapes, self.shape)?,
+            self.isometry,
+            self.payload,
+            self.mask,
+            callbacks,
+            self.callback.as_str(),
+        )
+        .ok_or_else(|| E::custom(format!("unregistered trigger callback: {}", self.callback)))?;
+        area.set_occupants(occupants);
+        area.set_wake_requested(self.wake_requested);
+        Ok(area)
+    }
+}
+
+/// World as it is saved
+#[derive(Serialize)]
+#[serde(rename = "World", bound = "T: Serialize, B: Serialize")]
+struct WorldRef<'a, T, B, P: Broadphase> {
+    epsilon: Real,
+    iterations: u32,
+    margin: Real,
+    sleep_threshold: Real,
+    sleep_ticks: u32,
+    fixed_step: &'a FixedStep,
+    record_events: bool,
+    shapes: &'a ShapeTable,
+    kinematics: ObjectsRef<'a, KinematicBody<B>, P>,
+    statics: ObjectsRef<'a, StaticBody<B>, P>,
+    platforms: ObjectsRef<'a, MovingPlatform<B>, P>,
+    triggers: TriggersRef<'a, T, B, P>,
+    #[cfg(feature = "2d")]
+    tile_maps: TileMapsRef<'a, B, P>,
+}
+
+/// World as it is loaded
+#[derive(Deserialize)]
+#[serde(rename = "World")]
+struct WorldData<T, B> {
+    epsilon: Real,
+    iterations: u32,
+    margin: Real,
+    sleep_threshold: Real,
+    sleep_ticks: u32,
+    fixed_step: FixedStep,
+    record_events: bool,
+    shapes: Vec<ShapeData>,
+    kinematics: Vec<SavedSlot<SavedObject<KinematicBody<B>>>>,
+    statics: Vec<SavedSlot<SavedObject<StaticBody<B>>>>,
+    platforms: Vec<SavedSlot<SavedObject<MovingPlatform<B>>>>,
+    triggers: Vec<SavedSlot<TriggerData<T>>>,
+    #[cfg(feature = "2d")]
+    tile_maps: Vec<SavedSlot<TileMap<B>>>,
+}
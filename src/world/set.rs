@@ -2,133 +2,336 @@
 //! Guarantee that the reference to the bodies are
 //! maintained as long as they are part of the physics world.
 
-use super::{aabb::Aabb, Shared};
+use super::{
+    aabb::Aabb,
+    broadphase::{Broadphase, Bvh, Partition},
+    handle::Handle,
+    Shared,
+};
 use crate::object::Object;
-use alloc::{sync::Arc, vec::Vec};
-use bvh_arena::Bvh;
+use alloc::vec::Vec;
 use delegate::delegate;
-use spin::RwLock;
+use parry::math::Real;
 
-/// Store a set of elements
-pub struct Set<O> {
-    /// List of objects in the set
-    pub(crate) objects: Vec<Shared<O>>,
+/// Handle to an object in the partition of a set
+type Proxy<O, P> = <<P as Broadphase>::Partition<Shared<O>> as Partition<Shared<O>>>::Handle;
+
+/// Store a set of elements partitioned with the given broadphase
+pub struct Set<O, P: Broadphase = Bvh> {
+    /// Slots holding the objects in the set
+    slots: Vec<Slot<O, Proxy<O, P>>>,
+
+    /// Indices of the empty slots
+    free: Vec<u32>,
+
+    /// Number of objects in the set
+    len: usize,
+
+    /// Number of objects reinserted in the partition since it was last rebuilt
+    reinserted: usize,
+
+    /// Specify if the partition must be rebuilt on the next refit
+    dirty: bool,
 
     /// Partitionning of the objects in the set
-    pub(crate) partition: Bvh<Shared<O>, Aabb>,
+    pub(crate) partition: P::Partition<Shared<O>>,
+}
+
+/// Slot holding an object of the set
+struct Slot<O, H> {
+    /// Incremented each time the object of the slot is removed
+    generation: u32,
+
+    /// Object stored in the slot
+    object: Option<Shared<O>>,
+
+    /// Bounds of the object in the partition
+    bounds: Aabb,
+
+    /// Handle of the object in the partition
+    proxy: Option<H>,
 }
 
 /// Generate a default set for this collection
-impl<O> Default for Set<O> {
+impl<O, P: Broadphase> Default for Set<O, P> {
     fn default() -> Self {
         Self {
-            objects: Default::default(),
+            slots: Default::default(),
+            free: Default::default(),
+            len: 0,
+            reinserted: 0,
+            dirty: false,
             partition: Default::default(),
         }
     }
 }
 
-impl<O> Set<O> {
+impl<O, P: Broadphase> Set<O, P> {
     /// Create a new empty set with a predefined capacity
     #[inline]
     pub fn with_capacity(capacity: usize) -> Self {
         Self {
-            objects: Vec::with_capacity(capacity),
-            partition: Bvh::default(),
+            slots: Vec::with_capacity(capacity),
+            free: Vec::new(),
+            len: 0,
+            reinserted: 0,
+            dirty: false,
+            partition: Default::default(),
         }
     }
 
     // Expose some methods from the underlying vector
     delegate! {
-        to self.objects {
-            pub fn len(&self) -> usize;
-            pub fn is_empty(&self) -> bool;
+        to self.slots {
             pub fn reserve(&mut self, additional: usize);
             pub fn reserve_exact(&mut self, additional: usize);
             pub fn shrink_to_fit(&mut self);
             pub fn shrink_to(&mut self, min_capacity: usize);
-            pub fn iter(&self) -> impl Iterator<Item = &Arc<RwLock<O>>>;
-            pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Arc<RwLock<O>>>;
         }
     }
 
-    /// Store a new element in this set
-    pub fn store(&mut self, object: Shared<O>) {
-        self.objects.push(object);
+    /// Number of objects in the set
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
     }
 
-    /// Remove an element from this set but don't look into the partition
-    /// Prefer using `remove` instead.
-    pub fn quick_remove(&mut self, object: &Shared<O>) -> bool {
-        // find the position of the object in the list
-        for (index, value) in self.objects.iter().enumerate() {
-            if Arc::ptr_eq(object, value) {
-                // We found the index, create an handle and remove the object.
-                self.objects.swap_remove(index);
+    /// Check if the set is empty
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
 
-                // once found, stop the iteration
-                return true;
-            }
+    /// Iterate over the objects in the set
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &Shared<O>> {
+        self.slots.iter().filter_map(|slot| slot.object.as_ref())
+    }
+
+    /// Iterate over the objects in the set
+    #[inline]
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Shared<O>> {
+        self.slots
+            .iter_mut()
+            .filter_map(|slot| slot.object.as_mut())
+    }
+
+    /// Iterate over the objects in the set along with their handles
+    pub fn entries(&self) -> impl Iterator<Item = (Handle<O>, &Shared<O>)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.object
+                .as_ref()
+                .map(|object| (Handle::new(index as u32, slot.generation), object))
+        })
+    }
+
+    /// Iterate over the slots of the set with their generation, including the empty ones
+    pub(crate) fn slots(&self) -> impl ExactSizeIterator<Item = (u32, Option<&Shared<O>>)> {
+        self.slots
+            .iter()
+            .map(|slot| (slot.generation, slot.object.as_ref()))
+    }
+
+    /// Access the object referred to by the handle, if it is still in the set
+    #[inline]
+    pub fn get(&self, handle: Handle<O>) -> Option<&Shared<O>> {
+        self.slots
+            .get(handle.index() as usize)
+            .filter(|slot| slot.generation == handle.generation())
+            .and_then(|slot| slot.object.as_ref())
+    }
+
+    /// Check if the object referred to by the handle is still in the set
+    #[inline]
+    pub fn contains(&self, handle: Handle<O>) -> bool {
+        self.get(handle).is_some()
+    }
+
+    /// Store a new element in this set
+    pub fn store(&mut self, object: Shared<O>) -> Handle<O> {
+        self.len += 1;
+
+        // reuse an empty slot if there is one
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index as usize];
+            slot.object = Some(object);
+            slot.bounds = Aabb::default();
+            slot.proxy = None;
+            return Handle::new(index, slot.generation);
         }
-        false
+
+        let index = self.slots.len() as u32;
+        self.slots.push(Slot {
+            generation: 0,
+            object: Some(object),
+            bounds: Aabb::default(),
+            proxy: None,
+        });
+        Handle::new(index, 0)
     }
 
-    /// Reset the partition but don't update the objects' handles
+    /// Remove an element from this set but don't look into the partition.
+    /// The partition is rebuilt on the next refit.
+    /// Prefer using `clean_remove` instead.
+    pub fn quick_remove(&mut self, handle: Handle<O>) -> Option<Shared<O>> {
+        let slot = self
+            .slots
+            .get_mut(handle.index() as usize)
+            .filter(|slot| slot.generation == handle.generation())?;
+        let object = slot.object.take()?;
+
+        // invalidate the handles referring to this slot
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(handle.index());
+        self.len -= 1;
+        self.dirty = true;
+        Some(object)
+    }
+
+    /// Reset the partition, the objects are only added back on the next refit.
     /// Prefer using `repartition` instead.
     #[inline]
     pub fn quick_reset(&mut self) {
         self.partition.clear();
+        for slot in self.slots.iter_mut() {
+            slot.proxy = None;
+        }
+        self.dirty = true;
     }
 }
 
-impl<O> Set<O>
+#[cfg(feature = "serde")]
+impl<O, P: Broadphase> Set<O, P> {
+    /// Rebuild a set from its slots so that the handles remain valid.
+    /// The partition is rebuilt on the next refit.
+    pub(crate) fn from_slots(slots: impl IntoIterator<Item = (u32, Option<Shared<O>>)>) -> Self {
+        let mut set = Self::default();
+        for (index, (generation, object)) in slots.into_iter().enumerate() {
+            if object.is_some() {
+                set.len += 1;
+            } else {
+                set.free.push(index as u32);
+            }
+            set.slots.push(Slot {
+                generation,
+                object,
+                bounds: Aabb::default(),
+                proxy: None,
+            });
+        }
+
+        // reuse the lowest slots first
+        set.free.reverse();
+        set.dirty = true;
+        set
+    }
+}
+
+impl<O, P> Set<O, P>
 where
     O: Object,
+    P: Broadphase,
 {
     /// Store the element and add it to the partition too
-    pub fn add(&mut self, object: Shared<O>) {
-        // add the object to the list
-        self.objects.push(object.clone());
+    pub fn add(&mut self, object: Shared<O>) -> Handle<O> {
+        let bounds = object.read().aabb();
+        let proxy = self.partition.insert(object.clone(), bounds);
+        self.partition.update();
 
-        // lock the object with write access to add it to the partition
-        let mut mut_obj = object.write();
-        let handle = self.partition.insert(object.clone(), mut_obj.aabb());
-        mut_obj.set_handle(handle);
+        // add the object to the list
+        let handle = self.store(object);
+        let slot = &mut self.slots[handle.index() as usize];
+        slot.bounds = bounds;
+        slot.proxy = Some(proxy);
+        handle
     }
 
     /// Remove an element from this set
-    pub fn clean_remove(&mut self, object: &Shared<O>) -> bool {
-        // find the position of the object in the list
-        for (index, value) in self.objects.iter().enumerate() {
-            if Arc::ptr_eq(object, value) {
-                // We found the index, create an handle and remove the object.
-                self.objects.swap_remove(index);
-
-                // detach the handle from the object
-                let mut mut_obj = object.write();
-                let handle = mut_obj.handle();
-                mut_obj.unset_handle();
-
-                // use the handle to remove the object from the partition
-                if let Some(handle) = handle {
-                    self.partition.remove(handle);
-                }
-
-                // once found, stop the iteration
-                return true;
-            }
+    pub fn clean_remove(&mut self, handle: Handle<O>) -> Option<Shared<O>> {
+        // detach the object from the partition
+        let proxy = self
+            .slots
+            .get_mut(handle.index() as usize)
+            .filter(|slot| slot.generation == handle.generation())?
+            .proxy
+            .take();
+        if let Some(proxy) = proxy {
+            self.partition.remove(proxy);
+            self.partition.update();
         }
-        false
+
+        self.quick_remove(handle)
     }
 
     /// Compute a partitionning for the objects defined in this set
+    #[inline]
     pub fn repartition(&mut self) {
+        self.rebuild(0.0);
+    }
+
+    /// Update the partition in place.
+    /// Only the objects which left their bounds are reinserted, with bounds grown by the margin.
+    /// The partition is rebuilt once as many objects have been reinserted as the set holds.
+    pub fn refit(&mut self, margin: Real) {
+        if self.dirty {
+            self.rebuild(margin);
+            return;
+        }
+
+        for slot in self.slots.iter_mut() {
+            let Some(object) = slot.object.as_ref() else {
+                continue;
+            };
+            let aabb = object.read().aabb();
+
+            // the object still fits in its bounds
+            if slot.proxy.is_some() && slot.bounds.contains(&aabb) {
+                continue;
+            }
+
+            // move the object to its new bounds
+            if let Some(proxy) = slot.proxy.take() {
+                self.partition.remove(proxy);
+            }
+            slot.bounds = aabb.loosened(margin);
+            slot.proxy = Some(self.partition.insert(object.clone(), slot.bounds));
+            self.reinserted += 1;
+        }
+
+        // rebalance the tree now and then
+        if self.reinserted > self.len {
+            self.rebuild(margin);
+        } else {
+            self.partition.update();
+        }
+    }
+
+    /// Rebuild the partition from scratch with bounds grown by the margin
+    fn rebuild(&mut self, margin: Real) {
         self.partition.clear();
-        for object in &self.objects {
-            let mut mut_obj = object.write();
-            let handle = self.partition.insert(object.clone(), mut_obj.aabb());
-            mut_obj.set_handle(handle);
+        for slot in self.slots.iter_mut() {
+            let Some(object) = slot.object.as_ref() else {
+                continue;
+            };
+            slot.bounds = object.read().aabb().loosened(margin);
+            slot.proxy = Some(self.partition.insert(object.clone(), slot.bounds));
         }
+        self.partition.update();
+        self.reinserted = 0;
+        self.dirty = false;
+    }
+
+    /// Access the partition of this set, to tune the broadphase
+    #[inline]
+    pub fn partition(&self) -> &P::Partition<Shared<O>> {
+        &self.partition
+    }
+
+    /// Mutable access the partition of this set.
+    /// Call `repartition` after replacing it, or `update` on it after editing its volumes.
+    #[inline]
+    pub fn partition_mut(&mut self) -> &mut P::Partition<Shared<O>> {
+        &mut self.partition
     }
 
     /// Performs an overlap query between a provided AABB and this set.
@@ -0,0 +1,102 @@
+//! Slide kinematic bodies along the surfaces they hit
+
+use super::{broadphase::Broadphase, World};
+use crate::{
+    object::{character::DEFAULT_MAX_SLIDES, kinematic_body::KinematicBody, world_normal, Object},
+    Shared,
+};
+use nalgebra_glm::is_null;
+use parry::math::{Real, Vector};
+
+impl<B, T, P: Broadphase> World<T, B, P> {
+    /// Move a body along its motion and slide along the surfaces it hits.
+    /// The remaining motion is cast through the world again after each slide.
+    /// The body is the locked content of the shared kinematic body.
+    pub(crate) fn move_and_slide(
+        &self,
+        kinematic: &Shared<KinematicBody<B>>,
+        body: &mut KinematicBody<B>,
+        delta_time: Real,
+    ) {
+        let Some(mut controller) = body.controller().copied() else {
+            return;
+        };
+        controller.reset();
+
+        let mut isometry = *body.isometry();
+        let mut motion = body.next_isometry().translation.vector - isometry.translation.vector;
+        let mut velocity = body.velocity;
+
+        // time is normalized over the remaining motion
+        let mut elapsed: Real = 0.0;
+
+        for _ in 0..controller.max_slides() {
+            if is_null(&motion, self.epsilon) {
+                break;
+            }
+
+            // find the nearest surface along the remaining motion
+            let offset = elapsed * delta_time;
+            let duration = (1.0 - elapsed) * delta_time;
+            let Some((time, hit)) =
+                self.cast_moving_obstacles(kinematic, body, &isometry, &motion, offset, duration)
+            else {
+                // nothing in the way, complete the motion
+                isometry.translation.vector += motion;
+                break;
+            };
+            let normal = world_normal(&isometry, &hit);
+
+            // move up to the surface and keep a small gap with it
+            isometry.translation.vector += motion * time - normal * self.epsilon;
+            elapsed += (1.0 - elapsed) * time;
+            motion *= 1.0 - time;
+            controller.touch(&-normal);
+
+            // slide along the surface with the remaining motion
+            let dot = motion.dot(&normal);
+            if dot > 0.0 {
+                motion -= normal * dot;
+            }
+            let dot = velocity.dot(&normal);
+            if dot > 0.0 {
+                velocity -= normal * dot;
+            }
+        }
+
+        body.slide(isometry.translation.vector, velocity, controller);
+    }
+    /// Motion a body would achieve from where it stands, sliding along the surfaces it hits
+    /// instead of going through them. The body is the locked content of the shared kinematic body.
+    pub(crate) fn resolved_motion(
+        &self,
+        kinematic: &Shared<KinematicBody<B>>,
+        body: &KinematicBody<B>,
+        motion: &Vector<Real>,
+    ) -> Vector<Real> {
+        let mut isometry = *body.next_isometry();
+        let start = isometry.translation.vector;
+        let mut motion = *motion;
+
+        for _ in 0..DEFAULT_MAX_SLIDES {
+            if is_null(&motion, self.epsilon) {
+                break;
+            }
+            let Some((time, hit)) = self.cast_obstacles(kinematic, body, &isometry, &motion) else {
+                isometry.translation.vector += motion;
+                break;
+            };
+
+            // stop at the surface and keep the part of the motion along it
+            let normal = world_normal(&isometry, &hit);
+            isometry.translation.vector += motion * time;
+            motion *= 1.0 - time;
+            let dot = motion.dot(&normal);
+            if dot > 0.0 {
+                motion -= normal * dot;
+            }
+        }
+
+        isometry.translation.vector - start
+    }
+}
@@ -0,0 +1,45 @@
+//! Keep kinematic bodies on the ground when walking down slopes
+
+use super::{broadphase::Broadphase, World};
+use crate::{
+    object::{kinematic_body::KinematicBody, world_normal},
+    Shared,
+};
+
+impl<B, T, P: Broadphase> World<T, B, P> {
+    /// Pull a body which left the ground back onto it.
+    /// The body is the locked content of the shared kinematic body.
+    pub(crate) fn snap_to_ground(
+        &self,
+        kinematic: &Shared<KinematicBody<B>>,
+        body: &mut KinematicBody<B>,
+    ) {
+        let snap_distance = body.snap_distance();
+        if snap_distance <= 0.0 || !body.was_on_floor() || body.is_on_floor() {
+            return;
+        }
+
+        // do not snap a body moving away from the ground
+        let up = body.up().into_inner();
+        if body.velocity.dot(&up) > self.epsilon {
+            return;
+        }
+
+        // cast downward from the resolved position
+        let start = *body.next_isometry();
+        let fall = -up * snap_distance;
+        let Some((time, hit)) = self.cast_obstacles(kinematic, body, &start, &fall) else {
+            // the ground is too far below
+            return;
+        };
+
+        // the surface must be walkable to stand on it
+        let normal = -world_normal(&start, &hit);
+        if !body.is_floor(&normal) {
+            return;
+        }
+
+        let landed = start.translation.vector + fall * time;
+        body.snap(landed, &normal);
+    }
+}
@@ -0,0 +1,144 @@
+//! Capture the moving state of a world and go back to it, for rollback netcode.
+//! Objects are matched by their handles, the objects added or removed since
+//! the capture are left untouched.
+
+use crate::{
+    object::{kinematic_body::BodyState, moving_platform::PlatformState},
+    world::{broadphase::Broadphase, handle::KinematicHandle, step::FixedStep, World},
+};
+use alloc::vec::Vec;
+
+/// Moving state of a world: isometries, velocities and occupants of the trigger areas
+pub struct Snapshot<B = ()> {
+    /// State of the kinematic bodies along with the generation of their slot
+    kinematics: Vec<Option<(u32, BodyState)>>,
+
+    /// State of the moving platforms along with the generation of their slot
+    platforms: Vec<Option<(u32, PlatformState)>>,
+
+    /// Number of occupants of the trigger areas and their pending wake request,
+    /// along with the generation of their slot
+    triggers: Vec<Option<(u32, usize, bool)>>,
+
+    /// Occupants of all the trigger areas, one area after the other
+    occupants: Vec<KinematicHandle<B>>,
+
+    /// Time accumulated by the fixed timestep driver
+    fixed_step: FixedStep,
+}
+
+// implement the traits by hand to avoid requiring them on the payload type
+
+impl<B> Default for Snapshot<B> {
+    fn default() -> Self {
+        Self {
+            kinematics: Vec::new(),
+            platforms: Vec::new(),
+            triggers: Vec::new(),
+            occupants: Vec::new(),
+            fixed_step: FixedStep::default(),
+        }
+    }
+}
+
+impl<B> Clone for Snapshot<B> {
+    fn clone(&self) -> Self {
+        Self {
+            kinematics: self.kinematics.clone(),
+            platforms: self.platforms.clone(),
+            triggers: self.triggers.clone(),
+            occupants: self.occupants.clone(),
+            fixed_step: self.fixed_step,
+        }
+    }
+}
+
+impl<B, T, P: Broadphase> World<T, B, P> {
+    /// Capture the moving state of the world
+    #[inline]
+    pub fn snapshot(&self) -> Snapshot<B> {
+        let mut snapshot = Snapshot::default();
+        self.snapshot_into(&mut snapshot);
+        snapshot
+    }
+
+    /// Capture the moving state of the world into an existing snapshot to reuse its memory
+    pub fn snapshot_into(&self, snapshot: &mut Snapshot<B>) {
+        snapshot.kinematics.clear();
+        snapshot.kinematics.extend(
+            self.kinematic_set
+                .slots()
+                .map(|(generation, body)| Some((generation, body?.read().state()))),
+        );
+
+        snapshot.platforms.clear();
+        snapshot.platforms.extend(
+            self.platform_set
+                .slots()
+                .map(|(generation, platform)| Some((generation, platform?.read().state()))),
+        );
+
+        snapshot.triggers.clear();
+        snapshot.occupants.clear();
+        for (generation, area) in self.trigger_set.slots() {
+            let Some(area) = area else {
+                snapshot.triggers.push(None);
+                continue;
+            };
+            let area = area.read();
+            snapshot.occupants.extend(area.occupant_handles());
+            snapshot.triggers.push(Some((
+                generation,
+                area.occupant_count(),
+                area.is_waking_occupants(),
+            )));
+        }
+
+        snapshot.fixed_step = self.fixed_step;
+    }
+
+    /// Go back to a captured state.
+    /// The bodies are updated in place and the partitions are refitted around them.
+    /// No event is reported for the trigger areas the bodies enter or leave.
+    pub fn restore(&mut self, snapshot: &Snapshot<B>) {
+        for ((generation, body), saved) in self.kinematic_set.slots().zip(&snapshot.kinematics) {
+            if let (Some(body), Some((saved, state))) = (body, saved)
+                && generation == *saved
+            {
+                body.write().set_state(state);
+            }
+        }
+
+        for ((generation, platform), saved) in self.platform_set.slots().zip(&snapshot.platforms) {
+            if let (Some(platform), Some((saved, state))) = (platform, saved)
+                && generation == *saved
+            {
+                platform.write().set_state(state);
+            }
+        }
+
+        let mut occupants = snapshot.occupants.iter();
+        for ((generation, area), saved) in self.trigger_set.slots().zip(&snapshot.triggers) {
+            let Some((saved, count, wake_requested)) = saved else {
+                continue;
+            };
+            let bodies = occupants
+                .by_ref()
+                .take(*count)
+                .filter_map(|handle| Some((*handle, self.kinematic_set.get(*handle)?.clone())));
+            if let Some(area) = area
+                && generation == *saved
+            {
+                let mut area = area.write();
+                area.set_occupants(bodies);
+                area.set_wake_requested(*wake_requested);
+            } else {
+                bodies.for_each(drop);
+            }
+        }
+
+        self.fixed_step = snapshot.fixed_step;
+        self.kinematic_set.refit(self.margin);
+        self.platform_set.refit(self.margin);
+    }
+}
@@ -0,0 +1,203 @@
+//! Lift kinematic bodies on top of small obstacles
+
+use super::{aabb::Aabb, broadphase::Broadphase, World};
+use crate::{
+    object::{cast_shapes, kinematic_body::KinematicBody, world_normal, Object},
+    Shared,
+};
+use alloc::sync::Arc;
+#[cfg(feature = "2d")]
+use bvh_arena::BoundingVolume;
+use nalgebra_glm::is_null;
+use parry::{
+    math::{Isometry, Real, Vector},
+    query::{ShapeCastHit, ShapeCastOptions},
+    shape::Shape,
+};
+
+impl<B, T, P: Broadphase> World<T, B, P> {
+    /// Try to lift a body blocked by an obstacle on top of it.
+    /// The body is the locked content of the shared kinematic body.
+    pub(crate) fn step_up(
+        &self,
+        kinematic: &Shared<KinematicBody<B>>,
+        body: &mut KinematicBody<B>,
+        delta_time: Real,
+    ) {
+        let step_height = body.step_height();
+        if step_height <= 0.0 {
+            return;
+        }
+
+        // split the motions along the up vector
+        let up = body.up().into_inner();
+        let start = *body.isometry();
+        let desired = body.desired_motion() - up * body.desired_motion().dot(&up);
+        let achieved = body.next_isometry().translation.vector - start.translation.vector;
+        let achieved = achieved - up * achieved.dot(&up);
+
+        // the body was not blocked sideway
+        if is_null(&desired, self.epsilon) || is_null(&(desired - achieved), self.epsilon) {
+            return;
+        }
+
+        // cast upward, as high as the step allows
+        let rise = up * step_height;
+        let time = self
+            .cast_obstacles(kinematic, body, &start, &rise)
+            .map_or(1.0, |hit| hit.0);
+        let rise = rise * time;
+        if is_null(&rise, self.epsilon) {
+            return;
+        }
+        let mut raised = start;
+        raised.translation.vector += rise;
+
+        // cast forward from the raised position
+        let time = self
+            .cast_obstacles(kinematic, body, &raised, &desired)
+            .map_or(1.0, |hit| hit.0);
+        let forward = desired * time;
+        if forward.norm_squared() <= achieved.norm_squared() + self.epsilon {
+            return;
+        }
+        let mut moved = raised;
+        moved.translation.vector += forward;
+
+        // cast downward to land on top of the step
+        let fall = -rise;
+        let Some((time, hit)) = self.cast_obstacles(kinematic, body, &moved, &fall) else {
+            // there is no ground to land on
+            return;
+        };
+
+        // the surface must be walkable to stand on it
+        let normal = -world_normal(&moved, &hit);
+        if !body.is_floor(&normal) {
+            return;
+        }
+
+        let landed = moved.translation.vector + fall * time;
+        body.climb(landed, &normal, delta_time);
+    }
+}
+
+impl<B, T, P: Broadphase> World<T, B, P> {
+    /// Find the nearest obstacle hit by a kinematic body moving from the given isometry.
+    /// Static bodies, tiles, moving platforms and the other kinematic bodies are considered
+    /// where they stand. The time of impact is normalized over the motion.
+    #[inline]
+    pub(super) fn cast_obstacles(
+        &self,
+        kinematic: &Shared<KinematicBody<B>>,
+        body: &KinematicBody<B>,
+        isometry: &Isometry<Real>,
+        motion: &Vector<Real>,
+    ) -> Option<(Real, ShapeCastHit)> {
+        self.cast_moving_obstacles(kinematic, body, isometry, motion, 0.0, 0.0)
+    }
+
+    /// Find the nearest obstacle hit by a kinematic body moving from the given isometry.
+    /// Moving platforms and the other kinematic bodies start `offset` seconds into their
+    /// motion and keep moving for `duration` seconds while the body moves.
+    /// The surfaces already touched that the body moves away from are ignored.
+    /// The time of impact is normalized over the motion.
+    /// The body is the locked content of the shared kinematic body, which is not cast against.
+    pub(super) fn cast_moving_obstacles(
+        &self,
+        kinematic: &Shared<KinematicBody<B>>,
+        body: &KinematicBody<B>,
+        isometry: &Isometry<Real>,
+        motion: &Vector<Real>,
+        offset: Real,
+        duration: Real,
+    ) -> Option<(Real, ShapeCastHit)> {
+        let mut target = *isometry;
+        target.translation.vector += motion;
+        let aabb = Aabb::new(
+            body.shape().compute_swept_aabb(isometry, &target),
+            body.layer(),
+            body.mask(),
+        );
+
+        let options = ShapeCastOptions {
+            max_time_of_impact: 1.0,
+            stop_at_penetration: false,
+            ..Default::default()
+        };
+        let cast = |other: &Isometry<Real>, velocity: &Vector<Real>, shape: &dyn Shape| {
+            let mut other = *other;
+            other.translation.vector += velocity * offset;
+            let drift = velocity * duration;
+            let hit = cast_shapes(
+                isometry,
+                motion,
+                body.shape(),
+                &other,
+                &drift,
+                shape,
+                options,
+            )?;
+
+            // ignore the surfaces already touched that the motion moves away from
+            let relative = motion - drift;
+            if hit.time_of_impact <= self.epsilon
+                && relative.dot(&world_normal(isometry, &hit)) <= 0.0
+            {
+                return None;
+            }
+            Some(hit)
+        };
+
+        let mut nearest: Option<(Real, ShapeCastHit)> = None;
+        let mut keep = |hit: ShapeCastHit| {
+            if nearest.is_none_or(|(time, _)| hit.time_of_impact < time) {
+                nearest = Some((hit.time_of_impact, hit));
+            }
+        };
+
+        self.static_set.query(&aabb, |other| {
+            let other = other.read();
+            if let Some(hit) = cast(other.isometry(), &Vector::zeros(), other.shape())
+                && other.blocks(body, isometry, &hit, self.epsilon)
+            {
+                keep(hit);
+            }
+        });
+
+        #[cfg(feature = "2d")]
+        for map in self.tile_set.iter() {
+            let map = map.read();
+            if !aabb.overlaps(&map.aabb()) {
+                continue;
+            }
+            map.for_each_tile(aabb.aabb(), |cell, shape, tile_isometry| {
+                if let Some(hit) = cast(tile_isometry, &Vector::zeros(), shape.as_ref())
+                    && !map.is_internal_edge(cell, &hit.normal2, self.epsilon)
+                {
+                    keep(hit);
+                }
+            });
+        }
+
+        self.platform_set.query(&aabb, |other| {
+            let other = other.read();
+            if let Some(hit) = cast(other.isometry(), &other.velocity(), other.shape()) {
+                keep(hit);
+            }
+        });
+
+        self.kinematic_set.query(&aabb, |other| {
+            // the body being moved is already locked for writing
+            if Arc::ptr_eq(other, kinematic) {
+                return;
+            }
+            let other = other.read();
+            if let Some(hit) = cast(other.isometry(), &other.velocity(), other.shape()) {
+                keep(hit);
+            }
+        });
+
+        nearest
+    }
+}
@@ -0,0 +1,103 @@
+//! Fixed timestep driver for the world
+
+use parry::math::Real;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Default number of ticks per second
+const DEFAULT_TICK_RATE: Real = 60.0;
+
+/// Default maximum number of catch-up ticks per frame
+const DEFAULT_MAX_TICKS: u32 = 5;
+
+/// Accumulate frame time and split it into ticks of constant duration
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FixedStep {
+    /// Duration of a single tick
+    tick: Real,
+
+    /// Maximum number of ticks to run in a single frame
+    max_ticks: u32,
+
+    /// Time left over from the previous frames
+    accumulator: Real,
+}
+
+impl FixedStep {
+    /// Create a new fixed step driver running at the given tick rate
+    #[inline]
+    pub fn new(tick_rate: Real, max_ticks: u32) -> Self {
+        Self {
+            tick: 1.0 / tick_rate,
+            max_ticks,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Duration of a single tick
+    #[inline]
+    pub fn tick(&self) -> Real {
+        self.tick
+    }
+
+    /// Number of ticks per second
+    #[inline]
+    pub fn tick_rate(&self) -> Real {
+        1.0 / self.tick
+    }
+
+    /// Change the number of ticks per second
+    #[inline]
+    pub fn set_tick_rate(&mut self, tick_rate: Real) {
+        self.tick = 1.0 / tick_rate;
+    }
+
+    /// Maximum number of ticks to run in a single frame
+    #[inline]
+    pub fn max_ticks(&self) -> u32 {
+        self.max_ticks
+    }
+
+    /// Change the maximum number of ticks to run in a single frame
+    #[inline]
+    pub fn set_max_ticks(&mut self, max_ticks: u32) {
+        self.max_ticks = max_ticks;
+    }
+
+    /// Interpolation factor between the last two ticks
+    #[inline]
+    pub fn alpha(&self) -> Real {
+        self.accumulator / self.tick
+    }
+
+    /// Drop the time left over from the previous frames
+    #[inline]
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+    }
+
+    /// Add the frame time to the accumulator and return the number of ticks to run.
+    /// Time exceeding the maximum number of ticks is discarded.
+    pub fn advance(&mut self, frame_time: Real) -> u32 {
+        self.accumulator += frame_time;
+
+        let mut ticks = 0;
+        while self.accumulator >= self.tick && ticks < self.max_ticks {
+            self.accumulator -= self.tick;
+            ticks += 1;
+        }
+
+        // we could not catch up, avoid spiraling on the next frames
+        if ticks == self.max_ticks && self.accumulator >= self.tick {
+            self.accumulator %= self.tick;
+        }
+        ticks
+    }
+}
+
+impl Default for FixedStep {
+    fn default() -> Self {
+        Self::new(DEFAULT_TICK_RATE, DEFAULT_MAX_TICKS)
+    }
+}